@@ -0,0 +1,156 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    img::Color,
+    math::{Easing, Quaternion, Vec3},
+};
+
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        self * (1. - t) + other * t
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        self * (1. - t) + other * t
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        Color(self.0.lerp(other.0, t))
+    }
+}
+
+impl Lerp for Quaternion {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        self.slerp(other, t)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Key<T: Lerp> {
+    pub time: f64,
+    pub value: T,
+    // towards the next key
+    pub easing: Easing,
+}
+
+impl<T: Lerp> Key<T> {
+    pub const fn new(time: f64, value: T) -> Self {
+        Self {
+            time,
+            value,
+            easing: Easing::Linear,
+        }
+    }
+
+    pub const fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+}
+
+/// A value animated over time, held constant before the first and after the last key
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<T: Lerp> {
+    keys: Vec<Key<T>>,
+}
+
+impl<T: Lerp> Curve<T> {
+    pub fn new(mut keys: Vec<Key<T>>) -> Self {
+        assert!(!keys.is_empty(), "Expected at least one key");
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { keys }
+    }
+
+    pub fn constant(value: T) -> Self {
+        Self::new(vec![Key::new(0., value)])
+    }
+
+    pub fn keys(&self) -> &[Key<T>] {
+        &self.keys
+    }
+
+    pub fn at(&self, time: f64) -> T {
+        let next = self.keys.partition_point(|key| key.time <= time);
+        if next == 0 {
+            return self.keys[0].value;
+        }
+        if next == self.keys.len() {
+            return self.keys[next - 1].value;
+        }
+        let (a, b) = (self.keys[next - 1], self.keys[next]);
+        let t = a.easing.apply((time - a.time) / (b.time - a.time));
+        a.value.lerp(b.value, t)
+    }
+}
+
+/// Frames are numbered from 1, frame 1 is at time 0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sequence {
+    pub first_frame: u32,
+    pub last_frame: u32,
+    pub fps: f64,
+}
+
+impl Sequence {
+    pub const fn new(first_frame: u32, last_frame: u32, fps: f64) -> Self {
+        Self {
+            first_frame,
+            last_frame,
+            fps,
+        }
+    }
+
+    pub fn frames(&self) -> RangeInclusive<u32> {
+        self.first_frame..=self.last_frame
+    }
+
+    pub fn time(&self, frame: u32) -> f64 {
+        (frame as f64 - 1.) / self.fps
+    }
+
+    pub fn frame_duration(&self) -> f64 {
+        self.fps.recip()
+    }
+
+    pub fn file_stem(stem: &str, frame: u32) -> String {
+        format!("{stem}_{frame:04}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{Easing, Vec3};
+
+    use super::{Curve, Key, Sequence};
+
+    #[test]
+    fn test_curve() {
+        let curve = Curve::new(vec![
+            Key::new(2., Vec3::splat(4.)),
+            Key::new(0., Vec3::ZERO).with_easing(Easing::EASE_IN_OUT),
+            Key::new(3., Vec3::splat(1.)),
+        ]);
+        assert_eq!(curve.at(-1.), Vec3::ZERO);
+        assert!((curve.at(1.) - Vec3::splat(2.)).l1_norm() < 1e-9);
+        assert!(curve.at(0.5).x < 1.);
+        assert!((curve.at(2.5) - Vec3::splat(2.5)).l1_norm() < 1e-9);
+        assert_eq!(curve.at(10.), Vec3::splat(1.));
+    }
+
+    #[test]
+    fn test_sequence() {
+        let sequence = Sequence::new(1, 48, 24.);
+        assert_eq!(sequence.frames().count(), 48);
+        assert_eq!(sequence.time(1), 0.);
+        assert_eq!(sequence.time(25), 1.);
+        assert_eq!(Sequence::file_stem("img", 7), "img_0007");
+    }
+}
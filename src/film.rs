@@ -0,0 +1,388 @@
+use std::{f64::consts::PI, ops::Range};
+
+use crate::{
+    img::{Color, Image},
+    math::Vec3,
+    scene::{AovSample, Display, Region},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box { radius: f64 },
+    Tent { radius: f64 },
+    Gaussian { radius: f64, sigma: f64 },
+    Mitchell { radius: f64, b: f64, c: f64 },
+    Lanczos { radius: f64, tau: f64 },
+}
+
+impl Filter {
+    // each sample only lands in the pixel that contains it
+    pub const PIXEL: Filter = Filter::Box { radius: 0.5 };
+    pub const MITCHELL: Filter = Filter::Mitchell {
+        radius: 2.,
+        b: 1. / 3.,
+        c: 1. / 3.,
+    };
+
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::Lanczos { radius, .. } => radius,
+        }
+    }
+
+    pub fn eval(&self, x: f64, y: f64) -> f64 {
+        self.eval_1d(x) * self.eval_1d(y)
+    }
+
+    fn eval_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        if x > self.radius() {
+            return 0.;
+        }
+        match *self {
+            Filter::Box { .. } => 1.,
+            Filter::Tent { radius } => radius - x,
+            Filter::Gaussian { radius, sigma } => {
+                let gaussian = |x: f64| (-x * x / (2. * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.)
+            }
+            Filter::Mitchell { radius, b, c } => mitchell(2. * x / radius, b, c),
+            Filter::Lanczos { tau, .. } => sinc(x) * sinc(x / tau),
+        }
+    }
+}
+
+fn mitchell(x: f64, b: f64, c: f64) -> f64 {
+    let value = if x > 1. {
+        (-b - 6. * c) * x.powi(3)
+            + (6. * b + 30. * c) * x.powi(2)
+            + (-12. * b - 48. * c) * x
+            + (8. * b + 24. * c)
+    } else {
+        (12. - 9. * b - 6. * c) * x.powi(3) + (-18. + 12. * b + 6. * c) * x.powi(2) + (6. - 2. * b)
+    };
+    value / 6.
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        return 1.;
+    }
+    (PI * x).sin() / (PI * x)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct FilmPixel {
+    color_sum: Vec3,
+    weight_sum: f64,
+    samples: u32,
+}
+
+/// Accumulates filtered samples, which can spread over neighbouring pixels
+#[derive(Debug, Clone)]
+pub struct Film {
+    display: Display,
+    filter: Filter,
+    // samples never spread outside of it, so the rest of the image stays empty
+    region: Region,
+    pixels: Box<[FilmPixel]>,
+}
+
+impl Film {
+    pub fn new(display: Display, filter: Filter) -> Self {
+        Self {
+            display,
+            filter,
+            region: Region::full(display),
+            pixels: vec![FilmPixel::default(); display.size()].into(),
+        }
+    }
+
+    pub fn with_region(mut self, region: Region) -> Self {
+        self.region = region.clamp(self.display);
+        self
+    }
+
+    pub fn display(&self) -> Display {
+        self.display
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    fn pixel_mut(&mut self, x: usize, y: usize) -> &mut FilmPixel {
+        &mut self.pixels[y * self.display.x() + x]
+    }
+
+    // x and y are continuous raster coordinates, so pixel (i, j) is centered on (i + 0.5, j + 0.5)
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Color) {
+        let radius = self.filter.radius();
+        let x_range = pixel_range(x, radius, &self.region.x);
+        let y_range = pixel_range(y, radius, &self.region.y);
+        for py in y_range {
+            for px in x_range.clone() {
+                let weight = self.filter.eval(px as f64 + 0.5 - x, py as f64 + 0.5 - y);
+                if weight == 0. {
+                    continue;
+                }
+                let pixel = self.pixel_mut(px, py);
+                pixel.color_sum += color.0 * weight;
+                pixel.weight_sum += weight;
+            }
+        }
+        if x >= 0. && y >= 0. && self.region.contains(x as u32, y as u32) {
+            self.pixel_mut(x as usize, y as usize).samples += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Film) {
+        for (pixel, other) in self.pixels.iter_mut().zip(other.pixels.iter()) {
+            pixel.color_sum += other.color_sum;
+            pixel.weight_sum += other.weight_sum;
+            pixel.samples += other.samples;
+        }
+    }
+
+    pub fn to_image(&self) -> Image {
+        let mut img = Image::zeros(self.display);
+        for (i, pixel) in self.pixels.iter().enumerate() {
+            let (x, y) = (i % self.display.x(), i / self.display.x());
+            if pixel.weight_sum > 0. {
+                *img.at_mut(x, y) = (pixel.color_sum / pixel.weight_sum).into();
+            }
+        }
+        img
+    }
+
+    pub fn sample_counts(&self) -> impl Iterator<Item = u32> + '_ {
+        self.pixels.iter().map(|pixel| pixel.samples)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    Depth,
+    Normal,
+    Albedo,
+    MaterialId,
+    ObjectId,
+    Direct,
+    Indirect,
+    SampleCount,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 8] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::MaterialId,
+        Aov::ObjectId,
+        Aov::Direct,
+        Aov::Indirect,
+        Aov::SampleCount,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::MaterialId => "material_id",
+            Aov::ObjectId => "object_id",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::SampleCount => "sample_count",
+        }
+    }
+
+    pub fn is_color(&self) -> bool {
+        matches!(self, Aov::Albedo | Aov::Direct | Aov::Indirect)
+    }
+
+    fn value(&self, sample: &AovSample) -> Option<Color> {
+        match self {
+            Aov::Depth => sample.hit.map(|hit| Color::gray(hit.depth)),
+            Aov::Normal => sample.hit.map(|hit| hit.normal.into()),
+            Aov::Albedo => sample.hit.map(|hit| hit.albedo),
+            Aov::MaterialId => sample.hit.map(|hit| id_color(hit.material_id)),
+            // object ids are small indices, so they are scrambled to get distinct colors
+            Aov::ObjectId => sample
+                .hit
+                .map(|hit| id_color((hit.object_id as u64 + 1).wrapping_mul(0x9E3779B97F4A7C15))),
+            Aov::Direct => Some(sample.direct),
+            Aov::Indirect => Some(sample.indirect),
+            Aov::SampleCount => Some(Color::WHITE),
+        }
+    }
+}
+
+fn id_color(id: u64) -> Color {
+    let [r, g, b, ..] = id.to_le_bytes();
+    Color::from_rgb(r as f64, g as f64, b as f64) / 255.
+}
+
+/// Extra render layers, accumulated alongside the beauty film
+#[derive(Debug, Clone)]
+pub struct AovFilm {
+    layers: Vec<(Aov, Film)>,
+}
+
+impl AovFilm {
+    // lighting layers use the beauty filter so they still sum up to it,
+    // geometric layers are never blended outside of their pixel
+    pub fn new(display: Display, aovs: &[Aov], filter: Filter) -> Self {
+        let layers = aovs
+            .iter()
+            .map(|&aov| {
+                let filter = match aov {
+                    Aov::Direct | Aov::Indirect => filter,
+                    _ => Filter::PIXEL,
+                };
+                (aov, Film::new(display, filter))
+            })
+            .collect();
+        Self { layers }
+    }
+
+    pub fn with_region(mut self, region: Region) -> Self {
+        for (_, film) in self.layers.iter_mut() {
+            film.region = region.clamp(film.display);
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn add_sample(&mut self, x: f64, y: f64, sample: &AovSample) {
+        for (aov, film) in self.layers.iter_mut() {
+            if let Some(value) = aov.value(sample) {
+                film.add_sample(x, y, value);
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &AovFilm) {
+        for ((_, film), (_, other)) in self.layers.iter_mut().zip(other.layers.iter()) {
+            film.merge(other);
+        }
+    }
+
+    pub fn to_images(&self) -> Vec<(Aov, Image)> {
+        self.layers
+            .iter()
+            .map(|(aov, film)| {
+                let img = match aov {
+                    Aov::SampleCount => {
+                        let mut img = Image::zeros(film.display);
+                        for (i, count) in film.sample_counts().enumerate() {
+                            let (x, y) = (i % film.display.x(), i / film.display.x());
+                            *img.at_mut(x, y) = Color::gray(count as f64);
+                        }
+                        img
+                    }
+                    _ => film.to_image(),
+                };
+                if aov.is_color() {
+                    (*aov, img)
+                } else {
+                    (*aov, img.non_color())
+                }
+            })
+            .collect()
+    }
+}
+
+// the pixels within radius of coordinate, limited to pixels
+fn pixel_range(coordinate: f64, radius: f64, pixels: &Range<u32>) -> Range<usize> {
+    let (first, last) = (pixels.start as f64, pixels.end as f64);
+    let start = (coordinate - 0.5 - radius).ceil().clamp(first, last) as usize;
+    let end = ((coordinate - 0.5 + radius).floor() + 1.).clamp(first, last) as usize;
+    start..end.max(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Film, Filter};
+    use crate::{
+        img::Color,
+        scene::{Display, Region},
+    };
+
+    #[test]
+    fn test_constant_color_is_preserved() {
+        let display = Display::new(8, 6);
+        let filters = [
+            Filter::PIXEL,
+            Filter::Tent { radius: 1.5 },
+            Filter::Gaussian {
+                radius: 1.5,
+                sigma: 0.5,
+            },
+            Filter::MITCHELL,
+            Filter::Lanczos {
+                radius: 3.,
+                tau: 3.,
+            },
+        ];
+        for filter in filters {
+            let mut film = Film::new(display, filter);
+            for i in 0..display.x() * 4 {
+                for j in 0..display.y() * 4 {
+                    let (x, y) = ((i as f64 + 0.5) / 4., (j as f64 + 0.5) / 4.);
+                    film.add_sample(x, y, Color::from_rgb(0.2, 0.4, 0.8));
+                }
+            }
+            for color in film.to_image().data() {
+                let error = (color.0 - Color::from_rgb(0.2, 0.4, 0.8).0).l1_norm();
+                assert!(error < 1e-9, "{filter:?} gave {color:?}");
+            }
+            assert!(film.sample_counts().all(|count| count == 16));
+        }
+    }
+
+    #[test]
+    fn test_pixel_filter_stays_in_pixel() {
+        let mut film = Film::new(Display::new(3, 3), Filter::PIXEL);
+        film.add_sample(1.25, 1.75, Color::WHITE);
+        let img = film.to_image();
+        let lit = img.data().iter().filter(|c| **c == Color::WHITE).count();
+        assert_eq!(lit, 1);
+        assert_eq!(img.data()[4], Color::WHITE);
+    }
+
+    #[test]
+    fn test_region_stays_inside() {
+        let display = Display::new(10, 8);
+        let region = Region::new(3..6, 2..5);
+        let wide = Filter::Gaussian {
+            radius: 3.,
+            sigma: 1.5,
+        };
+        for filter in [Filter::MITCHELL, wide] {
+            let mut film = Film::new(display, filter).with_region(region.clone());
+            for (x, y) in display {
+                if region.contains(x, y) {
+                    film.add_sample(x as f64 + 0.5, y as f64 + 0.5, Color::WHITE);
+                }
+            }
+            let img = film.to_image();
+            for (x, y) in display {
+                // images are read from the top row down
+                let color = img.at(x as usize, (display.y - y - 1) as usize);
+                if region.contains(x, y) {
+                    assert!((color.0 - Color::WHITE.0).l1_norm() < 1e-9, "{filter:?}");
+                } else {
+                    assert_eq!(color, Color::BLACK, "{filter:?} at {x} {y}");
+                }
+            }
+        }
+    }
+}
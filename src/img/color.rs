@@ -1,4 +1,6 @@
-use crate::math::Vec3;
+use std::ops::Div;
+
+use crate::math::{Mat3x3, Vec3};
 
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Color(pub Vec3);
@@ -30,12 +32,23 @@ impl Color {
         self.0.z
     }
 
-    pub fn to_rgb_bytes(self) -> [u8; 3] {
-        [
-            to_percent_byte(self.r()),
-            to_percent_byte(self.g()),
-            to_percent_byte(self.b()),
-        ]
+    pub fn luminance(self) -> f64 {
+        0.2126 * self.r() + 0.7152 * self.g() + 0.0722 * self.b()
+    }
+
+    pub fn encode_srgb(self) -> EncodedColor {
+        EncodedColor::from_rgb(
+            srgb_oetf(self.r()),
+            srgb_oetf(self.g()),
+            srgb_oetf(self.b()),
+        )
+    }
+
+    pub fn convert(self, from: ColorSpace, to: ColorSpace) -> Color {
+        if from == to {
+            return self;
+        }
+        Color(&to.xyz_to_rgb() * (&from.rgb_to_xyz() * self.0))
     }
 
     pub fn reflect_on(self, surface: Color) -> Color {
@@ -47,6 +60,14 @@ impl Color {
     }
 }
 
+impl Div<f64> for Color {
+    type Output = Color;
+
+    fn div(self, rhs: f64) -> Self::Output {
+        Color(self.0 / rhs)
+    }
+}
+
 impl From<Vec3> for Color {
     fn from(value: Vec3) -> Self {
         Self(value)
@@ -59,6 +80,171 @@ impl From<Color> for Vec3 {
     }
 }
 
+/// Display-encoded (non-linear) sRGB values, ready to be quantized
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EncodedColor(pub Vec3);
+
+impl EncodedColor {
+    pub const fn from_rgb(r: f64, g: f64, b: f64) -> Self {
+        Self(Vec3::new(r, g, b))
+    }
+
+    pub const fn r(self) -> f64 {
+        self.0.x
+    }
+
+    pub const fn g(self) -> f64 {
+        self.0.y
+    }
+
+    pub const fn b(self) -> f64 {
+        self.0.z
+    }
+
+    pub fn decode_srgb(self) -> Color {
+        Color::from_rgb(
+            srgb_eotf(self.r()),
+            srgb_eotf(self.g()),
+            srgb_eotf(self.b()),
+        )
+    }
+
+    pub fn from_rgb_bytes([r, g, b]: [u8; 3]) -> Self {
+        Self::from_rgb(r as f64 / 255., g as f64 / 255., b as f64 / 255.)
+    }
+
+    pub fn to_rgb_bytes(self) -> [u8; 3] {
+        [
+            to_percent_byte(self.r()),
+            to_percent_byte(self.g()),
+            to_percent_byte(self.b()),
+        ]
+    }
+}
+
+/// RGB primaries and white point that linear [`Color`] values are expressed in.
+/// Every space is converted through D65 XYZ, so ACEScg is Bradford adapted from D60
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorSpace {
+    #[default]
+    Srgb,
+    Rec2020,
+    AcesCg,
+}
+
+impl ColorSpace {
+    pub const fn rgb_to_xyz(self) -> Mat3x3 {
+        match self {
+            ColorSpace::Srgb => Mat3x3::from_row_vectors(
+                Vec3::new(0.4124564, 0.3575761, 0.1804375),
+                Vec3::new(0.2126729, 0.7151522, 0.0721750),
+                Vec3::new(0.0193339, 0.1191920, 0.9503041),
+            ),
+            ColorSpace::Rec2020 => Mat3x3::from_row_vectors(
+                Vec3::new(0.6369580, 0.1446169, 0.1688810),
+                Vec3::new(0.2627002, 0.6779981, 0.0593017),
+                Vec3::new(0.0000000, 0.0280727, 1.0609851),
+            ),
+            ColorSpace::AcesCg => Mat3x3::from_row_vectors(
+                Vec3::new(0.6522375419, 0.1282361360, 0.1699822492),
+                Vec3::new(0.2676721801, 0.6743399888, 0.0579878311),
+                Vec3::new(-0.0053818158, 0.0013690602, 1.0930705063),
+            ),
+        }
+    }
+
+    pub const fn xyz_to_rgb(self) -> Mat3x3 {
+        match self {
+            ColorSpace::Srgb => Mat3x3::from_row_vectors(
+                Vec3::new(3.2404548, -1.5371389, -0.4985315),
+                Vec3::new(-0.9692664, 1.8760109, 0.0415561),
+                Vec3::new(0.0556434, -0.2040259, 1.0572252),
+            ),
+            ColorSpace::Rec2020 => Mat3x3::from_row_vectors(
+                Vec3::new(1.7166513, -0.3556708, -0.2533663),
+                Vec3::new(-0.6666843, 1.6164812, 0.0157686),
+                Vec3::new(0.0176399, -0.0427706, 0.9421031),
+            ),
+            ColorSpace::AcesCg => Mat3x3::from_row_vectors(
+                Vec3::new(1.6605853266, -0.3152955610, -0.2415093276),
+                Vec3::new(-0.6599260633, 1.6083914697, 0.0172985947),
+                Vec3::new(0.0090025691, -0.0035668763, 0.9136433128),
+            ),
+        }
+    }
+}
+
+pub fn srgb_oetf(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(2.4f64.recip()) - 0.055
+    }
+}
+
+pub fn srgb_eotf(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
 fn to_percent_byte(x: f64) -> u8 {
-    (x * 256.).clamp(0., 255.).floor() as u8
+    (x * 255.).round().clamp(0., 255.) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::assert_within;
+
+    use super::{Color, ColorSpace, EncodedColor};
+
+    #[test]
+    fn test_srgb_round_trip() {
+        for i in 0..=255u8 {
+            let encoded = EncodedColor::from_rgb_bytes([i, i / 2, 255 - i]);
+            assert_eq!(
+                encoded.decode_srgb().encode_srgb().to_rgb_bytes(),
+                [i, i / 2, 255 - i]
+            );
+        }
+        assert_eq!(Color::gray(0.5).encode_srgb().to_rgb_bytes(), [188; 3]);
+    }
+
+    #[test]
+    fn test_byte_round_trip() {
+        for i in 0..=255u8 {
+            assert_eq!(EncodedColor::from_rgb_bytes([i; 3]).to_rgb_bytes(), [i; 3]);
+        }
+        assert_eq!(
+            EncodedColor::from_rgb(0.5, 0.999, 1.2).to_rgb_bytes(),
+            [128, 255, 255]
+        );
+        assert_eq!(
+            EncodedColor::from_rgb(-0.1, 0.001, 0.995).to_rgb_bytes(),
+            [0, 0, 254]
+        );
+    }
+
+    #[test]
+    fn test_color_space_round_trip() {
+        let color = Color::from_rgb(0.8, 0.3, 0.05);
+        for space in [ColorSpace::Rec2020, ColorSpace::AcesCg] {
+            let converted = color.convert(ColorSpace::Srgb, space);
+            assert_within(converted.convert(space, ColorSpace::Srgb).0, color.0, 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_white_is_preserved() {
+        for space in [ColorSpace::Rec2020, ColorSpace::AcesCg] {
+            // the published matrices disagree slightly on the exact D65 chromaticity
+            assert_within(
+                Color::WHITE.convert(ColorSpace::Srgb, space).0,
+                Color::WHITE.0,
+                1e-3,
+            );
+        }
+    }
 }
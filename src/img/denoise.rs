@@ -0,0 +1,205 @@
+use crate::math::Vec3;
+
+use super::{Color, Image};
+
+// B3 spline weights for the 5x5 a-trous kernel
+const KERNEL: [f64; 5] = [1. / 16., 1. / 4., 3. / 8., 1. / 4., 1. / 16.];
+
+/// Edge-avoiding a-trous wavelet filter (Dammertz et al. 2010), run on the
+/// linear image before tone mapping
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    pub iterations: u32,
+    pub color_sigma: f64,
+    pub normal_sigma: f64,
+    // relative to the depth of the center pixel
+    pub depth_sigma: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            color_sigma: 0.5,
+            normal_sigma: 0.3,
+            depth_sigma: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DenoiseFeatures<'a> {
+    pub albedo: Option<&'a Image>,
+    pub normal: Option<&'a Image>,
+    pub depth: Option<&'a Image>,
+}
+
+impl Denoiser {
+    pub fn apply(&self, img: &Image, features: &DenoiseFeatures) -> Image {
+        // filtering the lighting without the surface colors keeps textures sharp
+        let albedo = |i: usize| {
+            features
+                .albedo
+                .map(|albedo| albedo.data()[i].0)
+                .filter(|albedo| albedo.x > 0. && albedo.y > 0. && albedo.z > 0.)
+        };
+        let mut current: Vec<Vec3> = img
+            .data()
+            .iter()
+            .enumerate()
+            .map(|(i, color)| match albedo(i) {
+                Some(albedo) => divide(color.0, albedo),
+                None => color.0,
+            })
+            .collect();
+        let mut next = current.clone();
+        let (width, height) = (img.width() as isize, img.height() as isize);
+        for iteration in 0..self.iterations {
+            let step = 1isize << iteration;
+            let color_sigma = self.color_sigma / 2f64.powi(iteration as i32);
+            for y in 0..height {
+                for x in 0..width {
+                    let center = (y * width + x) as usize;
+                    let mut sum = Vec3::ZERO;
+                    let mut weight_sum = 0.;
+                    for (j, ky) in KERNEL.iter().enumerate() {
+                        for (i, kx) in KERNEL.iter().enumerate() {
+                            let qx = x + (i as isize - 2) * step;
+                            let qy = y + (j as isize - 2) * step;
+                            if !(0..width).contains(&qx) || !(0..height).contains(&qy) {
+                                continue;
+                            }
+                            let neighbour = (qy * width + qx) as usize;
+                            let weight = kx
+                                * ky
+                                * gaussian(
+                                    (current[center] - current[neighbour]).squared_magnitude(),
+                                    color_sigma,
+                                )
+                                * self.feature_weight(features, center, neighbour);
+                            sum += current[neighbour] * weight;
+                            weight_sum += weight;
+                        }
+                    }
+                    next[center] = sum / weight_sum;
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+        }
+        let mut denoised = img.clone();
+        for (i, (color, filtered)) in denoised.data_mut().iter_mut().zip(current).enumerate() {
+            *color = match albedo(i) {
+                Some(albedo) => Color(filtered).reflect_on(Color(albedo)),
+                None => Color(filtered),
+            };
+        }
+        denoised
+    }
+
+    fn feature_weight(&self, features: &DenoiseFeatures, center: usize, neighbour: usize) -> f64 {
+        let mut weight = 1.;
+        if let Some(normal) = features.normal {
+            let difference = normal.data()[center].0 - normal.data()[neighbour].0;
+            weight *= gaussian(difference.squared_magnitude(), self.normal_sigma);
+        }
+        if let Some(depth) = features.depth {
+            let center_depth = depth.data()[center].r();
+            let difference = (center_depth - depth.data()[neighbour].r()) / center_depth.max(1e-5);
+            weight *= gaussian(difference * difference, self.depth_sigma);
+        }
+        weight
+    }
+}
+
+fn gaussian(squared_distance: f64, sigma: f64) -> f64 {
+    (-squared_distance / (sigma * sigma)).exp()
+}
+
+fn divide(a: Vec3, b: Vec3) -> Vec3 {
+    Vec3::new(a.x / b.x, a.y / b.y, a.z / b.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        img::{Color, Image},
+        rand::{JavaLcg, RandSource},
+        scene::Display,
+    };
+
+    use super::{DenoiseFeatures, Denoiser};
+
+    const DISPLAY: Display = Display { x: 16, y: 16 };
+
+    // a gray image, darker on the left half, with uniform noise of the given amplitude
+    fn noisy(left: f64, right: f64, amplitude: f64) -> Image {
+        let mut rng = JavaLcg::from_seed(31);
+        let mut img = Image::zeros(DISPLAY);
+        for (x, y) in DISPLAY {
+            let base = if x < DISPLAY.x / 2 { left } else { right };
+            let noise = (rng.rand::<f64>() - 0.5) * amplitude;
+            *img.at_mut(x as usize, y as usize) = Color::gray(base + noise);
+        }
+        img
+    }
+
+    fn variance(img: &Image) -> f64 {
+        let mean = img.data().iter().map(|c| c.r()).sum::<f64>() / img.data().len() as f64;
+        img.data()
+            .iter()
+            .map(|c| (c.r() - mean).powi(2))
+            .sum::<f64>()
+            / img.data().len() as f64
+    }
+
+    // the mean of the columns on either side of the middle
+    fn edge_means(img: &Image) -> (f64, f64) {
+        let column = |x: usize| (0..img.height()).map(|y| img.at(x, y).r()).sum::<f64>();
+        let height = img.height() as f64;
+        let middle = img.width() / 2;
+        (column(middle - 1) / height, column(middle) / height)
+    }
+
+    #[test]
+    fn test_constant_image_is_unchanged() {
+        let img = noisy(0.3, 0.3, 0.);
+        let albedo = noisy(0.5, 0.5, 0.);
+        let features = DenoiseFeatures {
+            albedo: Some(&albedo),
+            ..Default::default()
+        };
+        for features in [DenoiseFeatures::default(), features] {
+            let denoised = Denoiser::default().apply(&img, &features);
+            for color in denoised.data() {
+                assert!((color.0 - Color::gray(0.3).0).l1_norm() < 1e-9, "{color:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_noise_is_reduced() {
+        let img = noisy(0.5, 0.5, 0.2);
+        let denoised = Denoiser::default().apply(&img, &DenoiseFeatures::default());
+        assert!(variance(&denoised) < variance(&img) / 10.);
+    }
+
+    #[test]
+    fn test_guide_edges_are_preserved() {
+        // a step too small for the color weight to stop, but marked in the normals
+        let img = noisy(0.45, 0.55, 0.1);
+        let mut normal = Image::zeros(DISPLAY);
+        for (x, y) in DISPLAY {
+            *normal.at_mut(x as usize, y as usize) =
+                Color::from_rgb((x < DISPLAY.x / 2) as u8 as f64, 0., 1.);
+        }
+        let features = DenoiseFeatures {
+            normal: Some(&normal),
+            ..Default::default()
+        };
+        let (left, right) = edge_means(&Denoiser::default().apply(&img, &features));
+        assert!((left - 0.45).abs() < 0.01 && (right - 0.55).abs() < 0.01);
+        let (left, right) =
+            edge_means(&Denoiser::default().apply(&img, &DenoiseFeatures::default()));
+        assert!(right - left < 0.05, "{left} {right}");
+    }
+}
@@ -1,11 +1,13 @@
-use crate::scene::Display;
+use crate::scene::{Display, Region};
 
-use super::Color;
+use super::{Color, ColorSpace, EncodedColor};
 
 #[derive(Debug, Clone)]
 pub struct Image {
     width: usize,
     height: usize,
+    color_space: ColorSpace,
+    non_color: bool,
     data: Box<[Color]>,
 }
 
@@ -14,10 +16,80 @@ impl Image {
         Self {
             width: size.x as usize,
             height: size.y as usize,
+            color_space: ColorSpace::default(),
+            non_color: false,
             data: vec![Color::BLACK; size.x as usize * size.y as usize].into(),
         }
     }
 
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    // data such as depth or normals, which is written out without any color transforms
+    pub fn non_color(mut self) -> Self {
+        self.non_color = true;
+        self
+    }
+
+    pub fn is_non_color(&self) -> bool {
+        self.non_color
+    }
+
+    pub fn normalized(&self) -> Self {
+        let max = self
+            .data
+            .iter()
+            .flat_map(|color| color.0)
+            .filter(|channel| channel.is_finite())
+            .fold(0., f64::max);
+        let mut img = self.clone();
+        if max > 0. {
+            for color in img.data_mut() {
+                *color = *color / max;
+            }
+        }
+        img
+    }
+
+    pub fn to_color_space(&self, color_space: ColorSpace) -> Self {
+        if self.non_color {
+            return self.clone();
+        }
+        let data = self
+            .data
+            .iter()
+            .map(|color| color.convert(self.color_space, color_space))
+            .collect();
+        Self {
+            color_space,
+            data,
+            ..*self
+        }
+    }
+
+    pub fn crop(&self, region: &Region) -> Self {
+        let size = region.size();
+        let mut img = Self {
+            data: vec![Color::BLACK; size.size()].into(),
+            width: size.x(),
+            height: size.y(),
+            ..self.clone()
+        };
+        for y in region.y.clone() {
+            for x in region.x.clone() {
+                let color = self.at(x as usize, self.height - y as usize - 1);
+                *img.at_mut((x - region.x.start) as usize, (y - region.y.start) as usize) = color;
+            }
+        }
+        img
+    }
+
     pub fn width(&self) -> usize {
         self.width
     }
@@ -41,4 +113,12 @@ impl Image {
     pub fn data_mut(&mut self) -> &mut [Color] {
         &mut self.data
     }
+
+    pub fn encoded_data(&self) -> impl Iterator<Item = EncodedColor> + '_ {
+        self.data.iter().map(|color| {
+            color
+                .convert(self.color_space, ColorSpace::Srgb)
+                .encode_srgb()
+        })
+    }
 }
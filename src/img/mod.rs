@@ -1,7 +1,10 @@
 mod color;
+mod denoise;
 mod image;
+mod tonemap;
 
 pub mod writer;
-pub use color::Color;
+pub use color::{Color, ColorSpace, EncodedColor};
+pub use denoise::{DenoiseFeatures, Denoiser};
 pub use image::Image;
-pub use writer::PPMWriter;
+pub use tonemap::ToneMapper;
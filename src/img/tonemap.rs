@@ -0,0 +1,142 @@
+use crate::math::{Mat3x3, Vec3};
+
+use super::Color;
+
+// AgX inset/outset matrices and log2 range as used in the Blender AgX configuration
+const AGX_INSET: Mat3x3 = Mat3x3::from_col_vectors(
+    Vec3::new(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+    Vec3::new(0.0784335999999992, 0.878468636469772, 0.0784336),
+    Vec3::new(0.0792237451477643, 0.0791661274605434, 0.879142973793104),
+);
+const AGX_OUTSET: Mat3x3 = Mat3x3::from_col_vectors(
+    Vec3::new(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+    Vec3::new(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+    Vec3::new(-0.0990297440797205, -0.0989611768448433, 1.15107367264116),
+);
+const AGX_MIN_EV: f64 = -12.47393;
+const AGX_MAX_EV: f64 = 4.026069;
+
+/// Maps scene-referred linear colors into the displayable `[0, 1]` range.
+///
+/// The output is still linear and has to go through a transfer function
+/// (eg. [`Color::encode_srgb`]) before quantization, which the image writers do.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ToneMapper {
+    /// Scales by `2^stops` and clamps to `[0, 1]`
+    Exposure(f64),
+    Reinhard,
+    /// Luminance based Reinhard where `white` is the smallest luminance mapped to 1
+    ExtendedReinhard {
+        white: f64,
+    },
+    /// Narkowicz's fit of the ACES filmic curve
+    #[default]
+    Aces,
+    AgX,
+}
+
+impl ToneMapper {
+    pub fn apply(&self, color: Color) -> Color {
+        match *self {
+            ToneMapper::Exposure(stops) => {
+                let scaled = color.0 * 2f64.powf(stops);
+                map_channels(scaled, |c| c.clamp(0., 1.))
+            }
+            ToneMapper::Reinhard => map_channels(color.0, |c| c.max(0.) / (1. + c.max(0.))),
+            ToneMapper::ExtendedReinhard { white } => {
+                let luminance = color.luminance();
+                if luminance <= 0. {
+                    return Color::BLACK;
+                }
+                let mapped = luminance * (1. + luminance / (white * white)) / (1. + luminance);
+                map_channels(color.0 * (mapped / luminance), |c| c.clamp(0., 1.))
+            }
+            ToneMapper::Aces => map_channels(color.0, |c| {
+                let c = c.max(0.);
+                (c * (2.51 * c + 0.03) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0., 1.)
+            }),
+            ToneMapper::AgX => {
+                let inset = &AGX_INSET * color.0;
+                let encoded = map_channels(inset, |c| {
+                    let ev = c.max(1e-10).log2().clamp(AGX_MIN_EV, AGX_MAX_EV);
+                    agx_contrast((ev - AGX_MIN_EV) / (AGX_MAX_EV - AGX_MIN_EV))
+                });
+                map_channels(&AGX_OUTSET * encoded.0, |c| c.clamp(0., 1.).powf(2.2))
+            }
+        }
+    }
+}
+
+fn map_channels(color: Vec3, f: impl Fn(f64) -> f64) -> Color {
+    Color::from_rgb(f(color.x), f(color.y), f(color.z))
+}
+
+// 6th order polynomial approximation of the default AgX sigmoid
+fn agx_contrast(x: f64) -> f64 {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+        - 0.00232
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::img::Color;
+
+    use super::ToneMapper;
+
+    // maps a gray ramp and checks the result stays in range and never decreases
+    fn assert_monotonic(tone_mapper: ToneMapper) {
+        let mut previous = 0.;
+        for i in 0..=200 {
+            let mapped = tone_mapper.apply(Color::gray(i as f64 * 0.1)).r();
+            assert!((0. ..=1.).contains(&mapped), "{tone_mapper:?} {mapped}");
+            assert!(mapped >= previous, "{tone_mapper:?} {mapped} < {previous}");
+            previous = mapped;
+        }
+    }
+
+    #[test]
+    fn test_reinhard() {
+        let reinhard = ToneMapper::Reinhard;
+        assert_eq!(reinhard.apply(Color::BLACK), Color::BLACK);
+        assert_eq!(reinhard.apply(Color::WHITE), Color::gray(0.5));
+        assert_eq!(reinhard.apply(Color::gray(3.)), Color::gray(0.75));
+        assert_eq!(reinhard.apply(Color::gray(-1.)), Color::BLACK);
+        assert_monotonic(reinhard);
+        let extended = ToneMapper::ExtendedReinhard { white: 4. };
+        assert_eq!(extended.apply(Color::BLACK), Color::BLACK);
+        assert!((extended.apply(Color::gray(4.)).r() - 1.).abs() < 1e-9);
+        assert_monotonic(extended);
+    }
+
+    #[test]
+    fn test_aces() {
+        let aces = ToneMapper::Aces;
+        assert_eq!(aces.apply(Color::BLACK), Color::BLACK);
+        assert!((aces.apply(Color::WHITE).r() - 2.54 / 3.16).abs() < 1e-9);
+        assert_eq!(aces.apply(Color::gray(1000.)), Color::WHITE);
+        assert_monotonic(aces);
+    }
+
+    #[test]
+    fn test_exposure() {
+        let neutral = ToneMapper::Exposure(0.);
+        assert_eq!(neutral.apply(Color::BLACK), Color::BLACK);
+        assert_eq!(neutral.apply(Color::WHITE), Color::WHITE);
+        assert_eq!(neutral.apply(Color::gray(0.3)), Color::gray(0.3));
+        assert_eq!(
+            ToneMapper::Exposure(1.).apply(Color::gray(0.25)),
+            Color::gray(0.5)
+        );
+        assert_eq!(
+            ToneMapper::Exposure(-2.).apply(Color::WHITE),
+            Color::gray(0.25)
+        );
+        assert_eq!(
+            ToneMapper::Exposure(1.).apply(Color::gray(0.75)),
+            Color::WHITE
+        );
+        assert_monotonic(ToneMapper::Exposure(2.));
+    }
+}
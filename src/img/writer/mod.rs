@@ -22,7 +22,7 @@ impl<'a> From<&'a Image> for PPMWriter<'a> {
 impl<'a> ImageWriter for PPMWriter<'a> {
     fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
         write!(writer, "P3\n{} {}\n255\n", self.0.width(), self.0.height())?;
-        for (i, datum) in self.0.data().iter().enumerate() {
+        for (i, datum) in self.0.encoded_data().enumerate() {
             if i % self.0.width() == 0 {
                 writeln!(writer)?;
             }
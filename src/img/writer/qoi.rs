@@ -1,6 +1,6 @@
 use std::io::Write;
 
-use crate::img::{Color, Image};
+use crate::img::{EncodedColor, Image};
 
 use super::ImageWriter;
 
@@ -18,8 +18,8 @@ struct QOIColor {
     b: u8,
 }
 
-impl From<&Color> for QOIColor {
-    fn from(value: &Color) -> Self {
+impl From<EncodedColor> for QOIColor {
+    fn from(value: EncodedColor) -> Self {
         let [r, g, b] = value.to_rgb_bytes();
         Self { r, g, b }
     }
@@ -82,11 +82,12 @@ impl<'a> ImageWriter for QOIWriter<'a> {
         writer.write_all(b"qoif")?;
         writer.write_all(&(self.0.width() as u32).to_be_bytes())?;
         writer.write_all(&(self.0.height() as u32).to_be_bytes())?;
-        writer.write_all(&[3, 0])?;
+        // colorspace 1 marks every channel as linear
+        writer.write_all(&[3, self.0.is_non_color() as u8])?;
         let mut index = [QOIColor::default(); 64];
         let mut prev_color = QOIColor::default();
         let mut run = 0;
-        for px in self.0.data() {
+        for px in self.0.encoded_data() {
             let color = QOIColor::from(px);
             if color == prev_color {
                 run += 1;
@@ -1,9 +1,10 @@
 #![allow(dead_code)]
-use material::{ColorMaterial, UniformDiffuse};
+use material::{ColorMaterial, Dielectric, Reflector, UniformDiffuse};
 use shapes::{ColorIndex, VertexIndex};
 use std::{
-    fs::{remove_file, File},
+    fs::{remove_file, rename, File},
     io::{BufWriter, Write},
+    path::Path,
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc, Mutex,
@@ -13,24 +14,38 @@ use std::{
 };
 
 use crate::{
+    animation::{Curve, Key, Sequence},
+    film::{Aov, AovFilm, Film, Filter},
     img::{
         writer::{ImageWriter, QOIWriter},
-        Color, Image, PPMWriter,
+        Color, ColorSpace, DenoiseFeatures, Denoiser, Image, ToneMapper,
     },
     material::Lambertian,
-    math::Vec3,
-    rand::thread_lcg,
-    scene::{Camera, Display, Scene},
-    shapes::{InvertedSphere, Shape, Sphere, TriangleMesh},
+    math::{AnimatedTransform, Easing, Keyframe, Quaternion, Transform, Vec2, Vec3},
+    rand::{thread_lcg, JavaLcg},
+    scene::{AovSample, Camera, Display, Region, Scene},
+    shapes::{
+        Cone, Csg, CsgOperation, Cuboid, Cylinder, Disk, Instance, InvertedSphere, Plane, Polygon,
+        Quad, Shape, Sphere, Torus, TriangleMesh,
+    },
+    spectrum::SampledWavelengths,
+    texture::{
+        Checker, ImageTexture, NoisePattern, NoiseTexture, NormalMap, TextureFilter, WrapMode,
+    },
     utils::{CartesianProduct, RangeChunks},
 };
 
+mod animation;
+mod film;
 mod img;
 mod material;
 mod math;
+mod projection;
 mod rand;
 mod scene;
 mod shapes;
+mod spectrum;
+mod texture;
 mod utils;
 
 pub const EPSILON: f64 = 1e-5;
@@ -72,20 +87,23 @@ fn my_world() -> impl Shape + Send + Sync {
         ([0b110, 0b111, 0b011], 0),
         ([0b110, 0b011, 0b010], 0),
     ];
-    let cube = Box::new(TriangleMesh::new(
-        vertices.clone(),
-        vec![Color::from_rgb(0.6, 0.4, 0.3)],
-        triangles.clone(),
+    let cube = Box::new(Cuboid::new(
+        Vec3::splat(-1.),
+        Vec3::splat(1.),
+        Color::from_rgb(0.6, 0.4, 0.3),
         REFLECTOR,
     ));
-    let outer = Box::new(TriangleMesh::new(
-        vertices.into_iter().map(|v| v * 20.).collect(),
-        vec![Color::WHITE],
-        triangles
-            .into_iter()
-            .map(|([a, b, c], i)| ([c, b, a], i))
-            .collect(),
-        REFLECTOR,
+    let outer = Box::new(Instance::new(
+        Arc::new(TriangleMesh::new(
+            vertices,
+            vec![Color::WHITE],
+            triangles
+                .into_iter()
+                .map(|([a, b, c], i)| ([c, b, a], i))
+                .collect(),
+            REFLECTOR,
+        )),
+        Transform::scale(Vec3::splat(20.)),
     ));
     let _outer = Box::new(InvertedSphere::new(
         Vec3::ZERO,
@@ -115,7 +133,7 @@ fn my_scene(display: Display) -> Scene<impl Shape + Send + Sync> {
     Scene {
         display,
         camera: Camera::from_display(
-            60. / 2.,
+            16.102113751986014,
             display,
             cam_pos,
             -cam_pos.normalize(),
@@ -130,41 +148,468 @@ fn my_scene(display: Display) -> Scene<impl Shape + Send + Sync> {
 fn weekend_scene(display: Display) -> Scene<impl Shape + Send + Sync> {
     type Reflector = UniformDiffuse;
     const REFLECTOR: Reflector = UniformDiffuse;
-    const WEEKEND_WORLD: [Sphere<Reflector>; 2] = [
-        Sphere::new(Vec3::new(0., 0., -1.), 0.5, Color::gray(0.5), REFLECTOR),
-        Sphere::new(
-            Vec3::new(0., -100.5, -1.),
-            100.,
+    let world: Vec<Box<dyn Shape<Material = ColorMaterial<Reflector>> + Send + Sync>> = vec![
+        Box::new(Sphere::new(
+            Vec3::new(0., 0., -1.),
+            0.5,
             Color::gray(0.5),
             REFLECTOR,
-        ),
+        )),
+        Box::new(Plane::new(
+            Vec3::new(0., -0.5, 0.),
+            Vec3::Y,
+            Color::gray(0.5),
+            REFLECTOR,
+        )),
     ];
     Scene {
         display,
-        camera: Camera::new(
-            74.29136217098426,
-            63.43494882292201,
-            Vec3::default(),
+        camera: Camera::look_at(
+            Vec3::ZERO,
             Vec3::NEG_Z,
             Vec3::Y,
+            45.,
+            display.x as f64 / display.y as f64,
         ),
         light_pos: Vec3::new(0., 0., 0.),
-        world: &WEEKEND_WORLD as &'static [Sphere<Reflector>],
+        world,
+        background_color: Color::from_rgb(0.5, 0.7, 1.),
+    }
+}
+
+fn texture_scene(display: Display) -> Scene<impl Shape + Send + Sync> {
+    type Reflector = Lambertian;
+    const REFLECTOR: Reflector = Lambertian;
+    let mut checker = Image::zeros(Display::new(8, 4));
+    for (x, y) in Display::new(8, 4) {
+        *checker.at_mut(x as usize, y as usize) = if (x + y) % 2 == 0 {
+            Color::from_rgb(0.8, 0.2, 0.1)
+        } else {
+            Color::gray(0.9)
+        };
+    }
+    let texture = ImageTexture::new(checker, ColorSpace::Srgb);
+    let sphere = Sphere::new(
+        Vec3::new(0., 2., 0.),
+        1.,
+        texture.clone().with_filter(TextureFilter::Nearest),
+        REFLECTOR,
+    );
+    let floor = TriangleMesh::new(
+        vec![
+            Vec3::new(-4., 1., -4.),
+            Vec3::new(4., 1., -4.),
+            Vec3::new(4., 1., 4.),
+            Vec3::new(-4., 1., 4.),
+        ],
+        vec![Checker::new(
+            2.,
+            texture.with_wrap(WrapMode::Mirror),
+            Color::gray(0.3),
+        )],
+        vec![([0, 2, 1], 0), ([0, 3, 2], 0)],
+        REFLECTOR,
+    )
+    .with_uvs(vec![
+        Vec2::new(0., 0.),
+        Vec2::new(3., 0.),
+        Vec2::new(3., 3.),
+        Vec2::new(0., 3.),
+    ]);
+    let mut rng = JavaLcg::from_seed(0x5EED);
+    let marble = Sphere::new(
+        Vec3::new(-2.5, 2., 0.),
+        1.,
+        NoiseTexture::new(
+            &mut rng,
+            NoisePattern::Marble {
+                octaves: 6,
+                distortion: 3.,
+            },
+            4.,
+            Color::gray(0.1),
+            Color::gray(0.9),
+        ),
+        REFLECTOR,
+    )
+    .with_motion(Vec3::new(-2.5, 2.3, 0.), 0., 1.);
+    let wood = Sphere::new(
+        Vec3::new(2.5, 2., 0.),
+        1.,
+        NoiseTexture::new(
+            &mut rng,
+            NoisePattern::Wood {
+                rings: 6.,
+                distortion: 1.5,
+            },
+            1.,
+            Color::from_rgb(0.35, 0.18, 0.07),
+            Color::from_rgb(0.7, 0.45, 0.25),
+        ),
+        REFLECTOR,
+    )
+    .with_normal_map(NormalMap::Bump {
+        height: Arc::new(NoiseTexture::new(
+            &mut rng,
+            NoisePattern::Fbm { octaves: 4 },
+            8.,
+            Color::BLACK,
+            Color::WHITE,
+        )),
+        strength: 0.05,
+    });
+    let world: Vec<Box<dyn Shape<Material = ColorMaterial<Reflector>> + Send + Sync>> = vec![
+        Box::new(sphere),
+        Box::new(marble),
+        Box::new(wood),
+        Box::new(floor),
+    ];
+    let target = Vec3::new(0., 2., 0.);
+    let cam_pos = Vec3::new(0., 5., 10.);
+    Scene {
+        display,
+        camera: Camera::from_display(
+            16.102113751986014,
+            display,
+            cam_pos,
+            target - cam_pos,
+            Vec3::Y,
+        )
+        .with_shutter(0., 1.)
+        .with_aperture(0.15, (target - cam_pos).magnitude())
+        .with_blades(6, 0.),
+        light_pos: Vec3::new(-5., 8., 10.),
+        world,
+        background_color: Color::from_rgb(0.5, 0.7, 1.),
+    }
+}
+
+// analytic primitives, a capped pipe with a cone on top, a ring, a disk on the floor
+// and a solid built from a box and a sphere
+fn primitives_scene(display: Display) -> Scene<impl Shape + Send + Sync> {
+    type Reflector = Lambertian;
+    const REFLECTOR: Reflector = Lambertian;
+    // shows the parametric uvs of the torus
+    let mut checker = Image::zeros(Display::new(16, 4));
+    for (x, y) in Display::new(16, 4) {
+        *checker.at_mut(x as usize, y as usize) =
+            Color::gray(if (x + y) % 2 == 0 { 0.2 } else { 0.8 });
+    }
+    let checker = ImageTexture::new(checker, ColorSpace::Srgb).with_filter(TextureFilter::Nearest);
+    // a five pointed star with a hole standing behind, next to its triangulation
+    let star = |center: Vec3| {
+        let point = |i: usize, radius: f64| {
+            let angle = i as f64 * std::f64::consts::TAU / 10.;
+            center + Vec3::new(-angle.sin(), angle.cos(), 0.) * radius
+        };
+        Polygon::new(
+            (0..10)
+                .map(|i| point(i, if i % 2 == 0 { 1.2 } else { 0.5 }))
+                .collect(),
+            Color::from_rgb(0.3, 0.6, 0.3),
+            REFLECTOR,
+        )
+        .with_hole((0..5).map(|i| point(9 - 2 * i, 0.25)).collect())
+    };
+    // a rounded box with a hole drilled through it
+    let drill = Cylinder::new(
+        Vec3::new(-2., 0.6, 0.),
+        Vec3::new(-2., 0.6, 2.),
+        0.3,
+        Color::from_rgb(0.8, 0.8, 0.3),
+        REFLECTOR,
+    )
+    .with_caps(true);
+    let part = Csg::new(
+        CsgOperation::Difference,
+        Csg::intersection(
+            Cuboid::new(
+                Vec3::new(-2.6, 0., 0.4),
+                Vec3::new(-1.4, 1.2, 1.6),
+                Color::from_rgb(0.3, 0.5, 0.8),
+                REFLECTOR,
+            ),
+            Sphere::new(
+                Vec3::new(-2., 0.6, 1.),
+                0.8,
+                Color::from_rgb(0.3, 0.5, 0.8),
+                REFLECTOR,
+            ),
+        ),
+        drill,
+    );
+    let world: Vec<Box<dyn Shape<Material = ColorMaterial<Reflector>> + Send + Sync>> = vec![
+        Box::new(Plane::new(Vec3::ZERO, Vec3::Y, Color::gray(0.5), REFLECTOR)),
+        Box::new(part),
+        Box::new(star(Vec3::new(-1.5, 2., -3.))),
+        Box::new(star(Vec3::new(1.5, 2., -3.)).triangulate()),
+        Box::new(
+            Cylinder::new(
+                Vec3::new(-2., 0., 0.),
+                Vec3::new(-2., 2., 0.),
+                0.6,
+                Color::from_rgb(0.7, 0.3, 0.2),
+                REFLECTOR,
+            )
+            .with_caps(true),
+        ),
+        Box::new(
+            Cone::new(
+                Vec3::new(-2., 2., 0.),
+                0.8,
+                Vec3::new(-2., 3.2, 0.),
+                0.,
+                Color::from_rgb(0.8, 0.7, 0.2),
+                REFLECTOR,
+            )
+            .with_caps(true),
+        ),
+        Box::new(Torus::new(
+            Vec3::new(1., 1.2, 0.),
+            Vec3::new(0., 1., 1.),
+            1.,
+            0.3,
+            checker,
+            REFLECTOR,
+        )),
+        Box::new(
+            Disk::new(
+                Vec3::new(1.5, 0.01, 2.5),
+                Vec3::Y,
+                0.8,
+                Color::from_rgb(0.2, 0.4, 0.7),
+                REFLECTOR,
+            )
+            .with_inner_radius(0.4),
+        ),
+    ];
+    let target = Vec3::new(0., 1.2, 0.);
+    let cam_pos = Vec3::new(0., 4., 9.);
+    Scene {
+        display,
+        camera: Camera::from_display(45. / 2., display, cam_pos, target - cam_pos, Vec3::Y),
+        light_pos: Vec3::new(-5., 8., 10.),
+        world,
+        background_color: Color::from_rgb(0.5, 0.7, 1.),
+    }
+}
+
+// dense flint and bk7 glass on a checkered floor, their edges fringe with color when SPECTRAL
+fn glass_scene(display: Display) -> Scene<impl Shape + Send + Sync> {
+    #[derive(Debug, Clone, Copy)]
+    enum Surface {
+        Diffuse(Lambertian),
+        Glass(Dielectric),
+    }
+
+    impl Reflector for Surface {
+        fn reflect(&self, dir: Vec3, normal: Vec3) -> Vec3 {
+            match self {
+                Surface::Diffuse(reflector) => reflector.reflect(dir, normal),
+                Surface::Glass(reflector) => reflector.reflect(dir, normal),
+            }
+        }
+
+        fn reflect_wavelength(&self, dir: Vec3, normal: Vec3, wavelength: f64) -> Vec3 {
+            match self {
+                Surface::Diffuse(reflector) => {
+                    reflector.reflect_wavelength(dir, normal, wavelength)
+                }
+                Surface::Glass(reflector) => reflector.reflect_wavelength(dir, normal, wavelength),
+            }
+        }
+
+        fn is_dispersive(&self) -> bool {
+            match self {
+                Surface::Diffuse(reflector) => reflector.is_dispersive(),
+                Surface::Glass(reflector) => reflector.is_dispersive(),
+            }
+        }
+    }
+
+    let world: Vec<Box<dyn Shape<Material = ColorMaterial<Surface>> + Send + Sync>> = vec![
+        Box::new(Plane::new(
+            Vec3::ZERO,
+            Vec3::Y,
+            Checker::new(1., Color::gray(0.9), Color::gray(0.1)),
+            Surface::Diffuse(Lambertian),
+        )),
+        Box::new(Sphere::new(
+            Vec3::new(-1.2, 1., 0.),
+            1.,
+            Color::WHITE,
+            Surface::Glass(Dielectric::DENSE_FLINT),
+        )),
+        Box::new(
+            Cuboid::new(
+                Vec3::new(0.6, 0., -0.6),
+                Vec3::new(2., 1.4, 0.6),
+                Color::from_rgb(0.9, 0.95, 1.),
+                Surface::Glass(Dielectric::BK7),
+            )
+            .with_rotation(Quaternion::from_axis_angle(Vec3::Y, 0.5)),
+        ),
+    ];
+    Scene {
+        display,
+        camera: Camera::look_at(
+            Vec3::new(0., 2.5, 6.),
+            Vec3::new(0., 0.8, 0.),
+            Vec3::Y,
+            30.,
+            display.x as f64 / display.y as f64,
+        ),
+        light_pos: Vec3::new(-5., 8., 10.),
+        world,
+        background_color: Color::WHITE,
+    }
+}
+
+// a textured sphere bouncing and spinning while the camera swings around it
+fn animated_scene(
+    display: Display,
+    shutter_open: f64,
+    shutter_close: f64,
+) -> Scene<impl Shape + Send + Sync> {
+    type Reflector = Lambertian;
+    const REFLECTOR: Reflector = Lambertian;
+    let mut checker = Image::zeros(Display::new(8, 4));
+    for (x, y) in Display::new(8, 4) {
+        *checker.at_mut(x as usize, y as usize) = if (x + y) % 2 == 0 {
+            Color::from_rgb(0.8, 0.2, 0.1)
+        } else {
+            Color::gray(0.9)
+        };
+    }
+    let ball = Arc::new(Sphere::new(
+        Vec3::ZERO,
+        1.,
+        ImageTexture::new(checker, ColorSpace::Srgb).with_filter(TextureFilter::Nearest),
+        REFLECTOR,
+    ));
+    let spin = |time: f64, turns: f64| {
+        Quaternion::from_axis_angle(Vec3::Y, turns * std::f64::consts::TAU)
+            * Quaternion::from_axis_angle(Vec3::Z, 0.4 * time)
+    };
+    let motion = AnimatedTransform::new(
+        [(0., 3.), (0.5, 1.), (1., 3.), (1.5, 1.), (2., 3.)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (time, height))| {
+                let easing = if i % 2 == 0 {
+                    Easing::EASE_IN
+                } else {
+                    Easing::EASE_OUT
+                };
+                Keyframe::new(
+                    time,
+                    Vec3::new(0., height, 0.),
+                    spin(time, i as f64 / 4.),
+                    Vec3::splat(1.),
+                )
+                .with_easing(easing)
+            })
+            .collect(),
+    );
+    let world: Vec<Box<dyn Shape<Material = ColorMaterial<Reflector>> + Send + Sync>> = vec![
+        Box::new(Instance::animated(ball, motion)),
+        Box::new(Plane::new(Vec3::ZERO, Vec3::Y, Color::gray(0.5), REFLECTOR)),
+        Box::new(Quad::new(
+            Vec3::new(-4., 0., -3.),
+            Vec3::new(8., 0., 0.),
+            Vec3::new(0., 4., 0.),
+            Color::from_rgb(0.2, 0.4, 0.7),
+            REFLECTOR,
+        )),
+    ];
+    let camera_path = Curve::new(vec![
+        Key::new(0., Vec3::new(0., 4., 10.)).with_easing(Easing::EASE_IN_OUT),
+        Key::new(2., Vec3::new(8., 3., 6.)),
+    ]);
+    let light_path = Curve::new(vec![
+        Key::new(0., Vec3::new(-5., 8., 10.)),
+        Key::new(2., Vec3::new(5., 8., 10.)),
+    ]);
+    let target = Vec3::new(0., 1.5, 0.);
+    let cam_pos = camera_path.at(shutter_open);
+    Scene {
+        display,
+        camera: Camera::from_display(
+            16.102113751986014,
+            display,
+            cam_pos,
+            target - cam_pos,
+            Vec3::Y,
+        )
+        .with_shutter(shutter_open, shutter_close),
+        light_pos: light_path.at(shutter_open),
+        world,
         background_color: Color::from_rgb(0.5, 0.7, 1.),
     }
 }
 
 fn draw() {
     let display = Display { x: 1280, y: 720 };
-    let scene = Arc::new(my_scene(display));
+    const FILE_STEM: &str = "img";
+    const SEQUENCE: Option<Sequence> = None;
+    let Some(sequence) = SEQUENCE else {
+        render(Arc::new(my_scene(display)), FILE_STEM);
+        return;
+    };
+    for frame in sequence.frames() {
+        let file_stem = Sequence::file_stem(FILE_STEM, frame);
+        // the beauty image is written last, so its existence marks a finished frame
+        if Path::new(&format!("{file_stem}.qoi")).exists() {
+            println!("Skipping frame {frame}, it was already rendered");
+            continue;
+        }
+        println!("Rendering frame {frame}");
+        let time = sequence.time(frame);
+        render(
+            Arc::new(animated_scene(
+                display,
+                time,
+                time + sequence.frame_duration() / 2.,
+            )),
+            &file_stem,
+        );
+    }
+}
+
+fn render<S: Shape + Send + Sync + 'static>(scene: Arc<Scene<S>>, file_stem: &str) {
+    let display = scene.display;
     let start_time = Instant::now();
     const SAMPLES: usize = 100;
     const BOUNCES: u16 = 50;
     const THREADS: usize = 16;
+    const TONE_MAPPER: ToneMapper = ToneMapper::Aces;
+    const WORKING_SPACE: ColorSpace = ColorSpace::Srgb;
+    const SPECTRAL: bool = false;
+    const FILTER: Filter = Filter::MITCHELL;
+    const AOVS: &[Aov] = &[];
+    const DENOISER: Option<Denoiser> = None;
+    let mut film_aovs = AOVS.to_vec();
+    if DENOISER.is_some() {
+        for feature in [Aov::Albedo, Aov::Normal, Aov::Depth] {
+            if !film_aovs.contains(&feature) {
+                film_aovs.push(feature);
+            }
+        }
+    }
+    // only these pixels are traced, cropping writes just them instead of the full frame
+    const REGION: Option<Region> = None;
+    const CROP: bool = false;
+    let region = REGION.map_or(Region::full(display), |region| region.clamp(display));
     let mut handles = Vec::with_capacity(THREADS);
-    let mut main_img = Image::zeros(display);
-    let x_chunk_iter = RangeChunks::new(0..display.x(), (display.x() + 1) / THREADS + 1);
-    let y_chunk_iter = RangeChunks::new(0..display.y(), (display.y() + 1) / THREADS + 1);
+    let region_size = region.size();
+    let x_chunk_iter = RangeChunks::new(
+        region.x.start as usize..region.x.end as usize,
+        (region_size.x() + 1) / THREADS + 1,
+    );
+    let y_chunk_iter = RangeChunks::new(
+        region.y.start as usize..region.y.end as usize,
+        (region_size.y() + 1) / THREADS + 1,
+    );
     let chunks_iter =
         CartesianProduct::new(x_chunk_iter, y_chunk_iter).map(|(x, y)| CartesianProduct::new(x, y));
     let (_, len) = chunks_iter.size_hint();
@@ -174,62 +619,119 @@ fn draw() {
         let scene = Arc::clone(&scene);
         let chunks_iter = Arc::clone(&chunks_iter);
         let progress = Arc::clone(&progress);
+        let film_aovs = film_aovs.clone();
+        let region = region.clone();
         handles.push(thread::spawn(move || {
-            let mut img = Image::zeros(display);
+            let mut film = Film::new(display, FILTER).with_region(region.clone());
+            let mut aov_film = AovFilm::new(display, &film_aovs, FILTER).with_region(region);
             while let Ok(Some(chunk)) = chunks_iter.lock().map(|mut c| c.next()) {
                 for (x, y) in chunk {
-                    let color = (0..SAMPLES)
-                        .map(|_| {
-                            let x_offset = thread_lcg::<f64>();
-                            let y_offset = thread_lcg::<f64>();
-                            let ray = scene.pixel_ray(x as f64 + x_offset, y as f64 + y_offset);
-                            scene.cast_ray(ray.clone(), BOUNCES)
-                        })
-                        .fold(Vec3::default(), |s, v| s + v.0);
-                    let pixel = img.at_mut(x, y);
-                    *pixel = (color / SAMPLES as f64).into();
+                    for _ in 0..SAMPLES {
+                        let sample_x = x as f64 + thread_lcg::<f64>();
+                        let sample_y = y as f64 + thread_lcg::<f64>();
+                        let lens_sample = Vec2::new(thread_lcg::<f64>(), thread_lcg::<f64>());
+                        let Some(ray) = scene.pixel_ray(sample_x, sample_y, lens_sample) else {
+                            // outside of what the projection covers
+                            film.add_sample(sample_x, sample_y, Color::BLACK);
+                            if !aov_film.is_empty() {
+                                aov_film.add_sample(sample_x, sample_y, &AovSample::default());
+                            }
+                            continue;
+                        };
+                        let ray = ray.with_time(scene.camera.sample_time(thread_lcg::<f64>()));
+                        // spectral renders trace the aovs with a separate rgb path
+                        let aov_sample = (!aov_film.is_empty())
+                            .then(|| scene.cast_ray_aov(ray.clone(), BOUNCES));
+                        let color = if SPECTRAL {
+                            let mut lambdas =
+                                SampledWavelengths::sample_uniform(thread_lcg::<f64>());
+                            scene
+                                .cast_ray_spectral(ray, BOUNCES, &mut lambdas)
+                                .to_color(&lambdas, WORKING_SPACE)
+                        } else if let Some(aov_sample) = &aov_sample {
+                            aov_sample.beauty
+                        } else {
+                            scene.cast_ray(ray, BOUNCES)
+                        };
+                        film.add_sample(sample_x, sample_y, color);
+                        if let Some(aov_sample) = aov_sample {
+                            aov_film.add_sample(sample_x, sample_y, &aov_sample);
+                        }
+                    }
                 }
                 progress.fetch_add(1, Ordering::Release);
             }
-            img
+            (film, aov_film)
         }));
     }
     while !handles.iter().all(JoinHandle::is_finished) {
         thread::sleep(std::time::Duration::from_millis(10));
         print_progress(len, progress.load(Ordering::Acquire), start_time);
     }
+    let mut film = Film::new(display, FILTER);
+    let mut aov_film = AovFilm::new(display, &film_aovs, FILTER);
     for handle in handles {
-        let img = handle.join().expect("Threads should not panic");
-        for (main, thread) in main_img.data_mut().iter_mut().zip(img.data()) {
-            if main.0 == Color::BLACK.0 {
-                *main = *thread;
-            }
-        }
+        let (thread_film, thread_aov_film) = handle.join().expect("Threads should not panic");
+        film.merge(&thread_film);
+        aov_film.merge(&thread_aov_film);
+    }
+    let crop = |img: Image| if CROP { img.crop(&region) } else { img };
+    let aov_images: Vec<_> = aov_film
+        .to_images()
+        .into_iter()
+        .map(|(aov, img)| (aov, crop(img)))
+        .collect();
+    let mut main_img = crop(film.to_image()).with_color_space(WORKING_SPACE);
+    if let Some(denoiser) = DENOISER {
+        let feature = |feature| {
+            aov_images
+                .iter()
+                .find_map(|(aov, img)| (*aov == feature).then_some(img))
+        };
+        let features = DenoiseFeatures {
+            albedo: feature(Aov::Albedo),
+            normal: feature(Aov::Normal),
+            depth: feature(Aov::Depth),
+        };
+        main_img = denoiser.apply(&main_img, &features);
     }
-    let brightest = main_img.data().iter().fold((0., 0., 0.), |c1, c2| {
-        (c2.r().max(c1.0), c2.g().max(c1.1), c2.b().max(c1.2))
-    });
-    let brightest = brightest.0.max(brightest.1).max(brightest.2);
     for color in main_img.data_mut() {
-        *color = color_correction((color.0 / brightest).into())
+        *color = TONE_MAPPER.apply(*color);
     }
     println!(
         "\nFinished rendering in {:.3?}",
         Instant::now().duration_since(start_time)
     );
-    const FILE_STEM: &str = "img";
-    let writer = QOIWriter::from(&main_img);
-    // let writer = PPMWriter::from(&main_img);
-    let file_name = format!("{}.{}", FILE_STEM, writer.extension().unwrap());
+    for (aov, img) in aov_images.into_iter().filter(|(aov, _)| AOVS.contains(aov)) {
+        let img = match aov {
+            Aov::Depth | Aov::SampleCount => img.normalized(),
+            Aov::Normal => {
+                let mut img = img;
+                for color in img.data_mut() {
+                    *color = ((color.0 + Vec3::splat(1.)) / 2.).into();
+                }
+                img
+            }
+            _ => img.with_color_space(WORKING_SPACE),
+        };
+        write_image(&img, &format!("{}_{}", file_stem, aov.name()));
+    }
+    write_image(&main_img, file_stem);
+}
+
+fn write_image(img: &Image, file_stem: &str) {
+    let writer = QOIWriter::from(img);
+    // let writer = img::writer::PPMWriter::from(img);
+    let file_name = format!("{}.{}", file_stem, writer.extension().unwrap());
+    // an interrupted write must not leave a complete looking file behind
+    let partial_name = format!("{file_name}.part");
     let _ = remove_file(&file_name);
-    let mut file = BufWriter::new(File::create(&file_name).unwrap());
+    let mut file = BufWriter::new(File::create(&partial_name).unwrap());
     writer
         .write_to(&mut file)
         .expect("Expected writing to succeed");
-}
-
-fn color_correction(input: Color) -> Color {
-    Vec3::new(input.0.x.sqrt(), input.0.y.sqrt(), input.0.z.sqrt()).into()
+    file.flush().expect("Expected writing to succeed");
+    rename(&partial_name, &file_name).expect("Expected renaming to succeed");
 }
 
 fn print_progress(len: Option<usize>, progress: usize, start_time: Instant) {
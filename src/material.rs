@@ -1,7 +1,13 @@
+use std::{
+    any::type_name,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
 use crate::{
     img::Color,
     math::{Ray, Vec3},
-    rand::{self, RandSource, ThreadLcg},
+    rand::{self, thread_lcg, RandSource, ThreadLcg},
+    spectrum::{SampledSpectrum, SampledWavelengths},
 };
 
 fn uniform_relfection(random: &mut impl RandSource, normal: Vec3) -> Vec3 {
@@ -15,6 +21,14 @@ fn uniform_relfection(random: &mut impl RandSource, normal: Vec3) -> Vec3 {
 
 pub trait Reflector {
     fn reflect(&self, dir: Vec3, normal: Vec3) -> Vec3;
+
+    fn reflect_wavelength(&self, dir: Vec3, normal: Vec3, _wavelength: f64) -> Vec3 {
+        self.reflect(dir, normal)
+    }
+
+    fn is_dispersive(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -35,9 +49,80 @@ impl Reflector for Lambertian {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct Dielectric {
+    // Cauchy's equation coefficients, with b in square micrometers
+    pub a: f64,
+    pub b: f64,
+}
+
+impl Dielectric {
+    pub const BK7: Dielectric = Dielectric::cauchy(1.5046, 0.00420);
+    pub const DENSE_FLINT: Dielectric = Dielectric::cauchy(1.7280, 0.01342);
+
+    pub const fn new(ior: f64) -> Self {
+        Self::cauchy(ior, 0.)
+    }
+
+    pub const fn cauchy(a: f64, b: f64) -> Self {
+        Self { a, b }
+    }
+
+    pub fn ior(&self, wavelength: f64) -> f64 {
+        let micrometers = wavelength / 1000.;
+        self.a + self.b / (micrometers * micrometers)
+    }
+
+    fn scatter(&self, dir: Vec3, normal: Vec3, ior: f64) -> Vec3 {
+        let (normal, eta) = if dir.dot(normal) < 0. {
+            (normal, ior.recip())
+        } else {
+            (-normal, ior)
+        };
+        let cos_in = (-dir.dot(normal)).min(1.);
+        let sin_out_squared = eta * eta * (1. - cos_in * cos_in);
+        let r0 = ((1. - eta) / (1. + eta)).powi(2);
+        let reflectance = r0 + (1. - r0) * (1. - cos_in).powi(5);
+        if sin_out_squared > 1. || thread_lcg::<f64>() < reflectance {
+            return normal.reflect(dir).normalize();
+        }
+        (dir * eta + normal * (eta * cos_in - (1. - sin_out_squared).sqrt())).normalize()
+    }
+}
+
+impl Reflector for Dielectric {
+    fn reflect(&self, dir: Vec3, normal: Vec3) -> Vec3 {
+        // sodium d-line, where glass IORs are usually quoted
+        self.scatter(dir, normal, self.ior(587.6))
+    }
+
+    fn reflect_wavelength(&self, dir: Vec3, normal: Vec3, wavelength: f64) -> Vec3 {
+        self.scatter(dir, normal, self.ior(wavelength))
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.b != 0.
+    }
+}
+
 pub trait Material {
     fn update_color(&self, outgoing: Color) -> Color;
     fn update_ray(&self, ray: Ray) -> Ray;
+    fn update_spectrum(
+        &self,
+        outgoing: SampledSpectrum,
+        lambdas: &SampledWavelengths,
+    ) -> SampledSpectrum;
+
+    fn update_ray_spectral(&self, ray: Ray, _lambdas: &mut SampledWavelengths) -> Ray {
+        self.update_ray(ray)
+    }
+
+    fn normal(&self) -> Vec3;
+    fn set_normal(&mut self, normal: Vec3);
+    fn albedo(&self) -> Color;
+    // identical materials share an id
+    fn id(&self) -> u64;
 }
 
 pub struct ColorMaterial<R: Reflector> {
@@ -65,4 +150,107 @@ impl<R: Reflector> Material for ColorMaterial<R> {
         ray.dir = self.reflector.reflect(ray.dir, self.normal);
         ray
     }
+
+    fn update_spectrum(
+        &self,
+        outgoing: SampledSpectrum,
+        lambdas: &SampledWavelengths,
+    ) -> SampledSpectrum {
+        outgoing * SampledSpectrum::from_rgb(self.color, lambdas)
+    }
+
+    fn update_ray_spectral(&self, mut ray: Ray, lambdas: &mut SampledWavelengths) -> Ray {
+        if !self.reflector.is_dispersive() {
+            return self.update_ray(ray);
+        }
+        lambdas.terminate_secondary();
+        ray.dir = self
+            .reflector
+            .reflect_wavelength(ray.dir, self.normal, lambdas.hero());
+        ray
+    }
+
+    fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    fn set_normal(&mut self, normal: Vec3) {
+        self.normal = normal;
+    }
+
+    fn albedo(&self) -> Color {
+        self.color
+    }
+
+    fn id(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        type_name::<R>().hash(&mut hasher);
+        for channel in self.color.0 {
+            channel.to_bits().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{assert_close, Vec3};
+
+    use super::{Dielectric, Reflector};
+
+    #[test]
+    fn test_cauchy_dispersion() {
+        for glass in [Dielectric::BK7, Dielectric::DENSE_FLINT] {
+            assert!(glass.ior(450.) > glass.ior(650.));
+        }
+        // the quoted n_d of BK7
+        assert!((Dielectric::BK7.ior(587.6) - 1.5168).abs() < 1e-3);
+        assert!(Dielectric::BK7.is_dispersive());
+        assert!(!Dielectric::new(1.5).is_dispersive());
+        assert_eq!(Dielectric::new(1.5).ior(450.), 1.5);
+    }
+
+    #[test]
+    fn test_total_internal_reflection() {
+        // leaving the glass at 60 degrees, past the critical angle of about 42
+        let angle = 60f64.to_radians();
+        let dir = Vec3::new(angle.sin(), angle.cos(), 0.);
+        for _ in 0..100 {
+            assert_close(
+                Dielectric::new(1.5).reflect(dir, Vec3::Y),
+                Vec3::new(angle.sin(), -angle.cos(), 0.),
+            );
+        }
+    }
+
+    #[test]
+    fn test_snells_law() {
+        let glass = Dielectric::new(1.5);
+        let angle = 30f64.to_radians();
+        let dir = Vec3::new(angle.sin(), -angle.cos(), 0.);
+        let sin_out = angle.sin() / 1.5;
+        let refracted = Vec3::new(sin_out, -(1. - sin_out * sin_out).sqrt(), 0.);
+        let mirrored = Vec3::new(angle.sin(), angle.cos(), 0.);
+        // only a few percent are reflected at this angle
+        let mut refractions = 0;
+        for _ in 0..100 {
+            let out = glass.reflect(dir, Vec3::Y);
+            if (out - mirrored).l1_norm() > 1e-9 {
+                assert_close(out, refracted);
+                refractions += 1;
+            }
+        }
+        assert!(refractions > 50);
+        // blue bends more than red
+        let bend =
+            |wavelength| Dielectric::DENSE_FLINT.reflect_wavelength(dir, Vec3::Y, wavelength);
+        let (mut blue, mut red) = (bend(450.), bend(650.));
+        while blue.y > 0. {
+            blue = bend(450.);
+        }
+        while red.y > 0. {
+            red = bend(650.);
+        }
+        assert!(blue.x < red.x);
+    }
 }
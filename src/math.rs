@@ -116,6 +116,14 @@ impl Vec3 {
     pub fn l1_norm(self) -> f64 {
         self.x.abs() + self.y.abs() + self.z.abs()
     }
+
+    // in radians
+    pub fn angle_to(self, other: Self) -> f64 {
+        self.normalize_or_zero()
+            .dot(other.normalize_or_zero())
+            .clamp(-1., 1.)
+            .acos()
+    }
 }
 
 impl From<Vec3> for [f64; 3] {
@@ -240,6 +248,56 @@ impl IndexMut<usize> for Vec3 {
     }
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Vec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vec2 {
+    pub const ZERO: Vec2 = Vec2 { x: 0., y: 0. };
+
+    pub const fn new(x: f64, y: f64) -> Self {
+        Vec2 { x, y }
+    }
+
+    pub fn dot(self, rhs: Self) -> f64 {
+        self.x * rhs.x + self.y * rhs.y
+    }
+
+    pub fn cross(self, rhs: Self) -> f64 {
+        self.x * rhs.y - self.y * rhs.x
+    }
+
+    pub fn magnitude(self) -> f64 {
+        self.dot(self).sqrt()
+    }
+}
+
+impl Mul<f64> for Vec2 {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        Self::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Mat3x3 {
     pub row1: Vec3,
@@ -522,26 +580,901 @@ impl IndexMut<usize> for Mat3x3 {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub const fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        points
+            .into_iter()
+            .map(|point| Self::new(point, point))
+            .reduce(|a, b| a.union(&b))
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            Vec3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            Vec3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        )
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    // radius of the sphere around the center enclosing the box
+    pub fn bounding_radius(&self) -> f64 {
+        self.size().magnitude() / 2.
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+            Vec3::new(
+                if i & 0b100 == 0 {
+                    self.min.x
+                } else {
+                    self.max.x
+                },
+                if i & 0b010 == 0 {
+                    self.min.y
+                } else {
+                    self.max.y
+                },
+                if i & 0b001 == 0 {
+                    self.min.z
+                } else {
+                    self.max.z
+                },
+            )
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Ray {
     pub start: Vec3,
     pub dir: Vec3,
+    // when in the shutter interval the ray was sent
+    pub time: f64,
 }
 
 impl Ray {
     pub const fn new(start: Vec3, dir: Vec3) -> Self {
-        Self { start, dir }
+        Self {
+            start,
+            dir,
+            time: 0.,
+        }
     }
 
     pub fn new_unit(start: Vec3, dir: Vec3) -> Self {
         Self::new(start, dir.normalize())
     }
 
+    pub const fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
+
     pub fn translate(&self, scale: f64) -> Ray {
-        Ray::new(self.point_at(scale), self.dir)
+        Ray::new(self.point_at(scale), self.dir).with_time(self.time)
     }
 
     pub fn point_at(&self, scale: f64) -> Vec3 {
         self.start + self.dir * scale
     }
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mat4x4 {
+    pub rows: [[f64; 4]; 4],
+}
+
+impl Mat4x4 {
+    pub const fn from_rows(rows: [[f64; 4]; 4]) -> Self {
+        Self { rows }
+    }
+
+    pub const fn identity() -> Self {
+        Self::from_rows([
+            [1., 0., 0., 0.],
+            [0., 1., 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub const fn from_affine(linear: &Mat3x3, translation: Vec3) -> Self {
+        let [r1, r2, r3] = [linear.row1, linear.row2, linear.row3];
+        Self::from_rows([
+            [r1.x, r1.y, r1.z, translation.x],
+            [r2.x, r2.y, r2.z, translation.y],
+            [r3.x, r3.y, r3.z, translation.z],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut rows = [[0.; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.rows[j][i];
+            }
+        }
+        Self::from_rows(rows)
+    }
+
+    // the upper left 3x3
+    pub fn linear(&self) -> Mat3x3 {
+        let row = |i: usize| Vec3::new(self.rows[i][0], self.rows[i][1], self.rows[i][2]);
+        Mat3x3::from_row_vectors(row(0), row(1), row(2))
+    }
+
+    pub fn translation(&self) -> Vec3 {
+        Vec3::new(self.rows[0][3], self.rows[1][3], self.rows[2][3])
+    }
+
+    // gauss-jordan elimination with partial pivoting
+    pub fn inverse(&self) -> Option<Self> {
+        let mut m = self.rows;
+        let mut inverse = Self::identity().rows;
+        for i in 0..4 {
+            let pivot = (i..4).max_by(|&a, &b| m[a][i].abs().total_cmp(&m[b][i].abs()))?;
+            if m[pivot][i] == 0. {
+                return None;
+            }
+            m.swap(i, pivot);
+            inverse.swap(i, pivot);
+            let recip = m[i][i].recip();
+            for j in 0..4 {
+                m[i][j] *= recip;
+                inverse[i][j] *= recip;
+            }
+            for k in 0..4 {
+                if k == i {
+                    continue;
+                }
+                let factor = m[k][i];
+                for j in 0..4 {
+                    m[k][j] -= factor * m[i][j];
+                    inverse[k][j] -= factor * inverse[i][j];
+                }
+            }
+        }
+        Some(Self::from_rows(inverse))
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        let [x, y, z, w] = self
+            .rows
+            .map(|row| row[0] * point.x + row[1] * point.y + row[2] * point.z + row[3]);
+        if w == 1. {
+            Vec3::new(x, y, z)
+        } else {
+            Vec3::new(x, y, z) / w
+        }
+    }
+
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        &self.linear() * vector
+    }
+}
+
+impl Mul<&Mat4x4> for &Mat4x4 {
+    type Output = Mat4x4;
+
+    fn mul(self, rhs: &Mat4x4) -> Self::Output {
+        let mut rows = [[0.; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.rows[i][k] * rhs.rows[k][j]).sum();
+            }
+        }
+        Mat4x4::from_rows(rows)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion::new(1., 0., 0., 0.);
+
+    pub const fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Self { w, x, y, z }
+    }
+
+    // angle in radians, counterclockwise looking down the axis
+    pub fn from_axis_angle(axis: Vec3, angle: f64) -> Self {
+        let (sin, cos) = (angle / 2.).sin_cos();
+        let axis = axis.normalize() * sin;
+        Self::new(cos, axis.x, axis.y, axis.z)
+    }
+
+    pub fn vector(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    pub fn dot(self, rhs: Self) -> f64 {
+        self.w * rhs.w + self.vector().dot(rhs.vector())
+    }
+
+    pub fn normalize(self) -> Self {
+        let recip = self.dot(self).sqrt().recip();
+        Self::new(
+            self.w * recip,
+            self.x * recip,
+            self.y * recip,
+            self.z * recip,
+        )
+    }
+
+    pub fn conjugate(self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        let q = self.vector();
+        let t = 2. * q.cross(v);
+        v + self.w * t + q.cross(t)
+    }
+
+    pub fn to_matrix(self) -> Mat3x3 {
+        let Self { w, x, y, z } = self.normalize();
+        Mat3x3::from_row_vectors(
+            Vec3::new(
+                1. - 2. * (y * y + z * z),
+                2. * (x * y - w * z),
+                2. * (x * z + w * y),
+            ),
+            Vec3::new(
+                2. * (x * y + w * z),
+                1. - 2. * (x * x + z * z),
+                2. * (y * z - w * x),
+            ),
+            Vec3::new(
+                2. * (x * z - w * y),
+                2. * (y * z + w * x),
+                1. - 2. * (x * x + y * y),
+            ),
+        )
+    }
+
+    // takes the shorter way around
+    pub fn slerp(self, other: Self, t: f64) -> Self {
+        let mut cos = self.dot(other);
+        let other = if cos < 0. {
+            cos = -cos;
+            Self::new(-other.w, -other.x, -other.y, -other.z)
+        } else {
+            other
+        };
+        let (a, b) = if cos > 1. - 1e-9 {
+            (1. - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1. - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        Self::new(
+            a * self.w + b * other.w,
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+        )
+        .normalize()
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Self;
+
+    // rotates by rhs then self
+    fn mul(self, rhs: Self) -> Self::Output {
+        let (a, b) = (self.vector(), rhs.vector());
+        let v = self.w * b + rhs.w * a + a.cross(b);
+        Self::new(self.w * rhs.w - a.dot(b), v.x, v.y, v.z)
+    }
+}
+
+/// An invertible transform with its inverse and the matrix for normals kept alongside
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    matrix: Mat4x4,
+    inverse: Mat4x4,
+    // inverse transpose of the linear part
+    normal_matrix: Mat3x3,
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self::from_parts(Mat4x4::identity(), Mat4x4::identity())
+    }
+
+    pub fn from_matrix(matrix: Mat4x4) -> Option<Self> {
+        let inverse = matrix.inverse()?;
+        Some(Self::from_parts(matrix, inverse))
+    }
+
+    pub fn from_affine(linear: &Mat3x3, translation: Vec3) -> Option<Self> {
+        Self::from_matrix(Mat4x4::from_affine(linear, translation))
+    }
+
+    fn from_parts(matrix: Mat4x4, inverse: Mat4x4) -> Self {
+        Self {
+            normal_matrix: inverse.linear().transpose(),
+            matrix,
+            inverse,
+        }
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        Self::from_parts(
+            Mat4x4::from_affine(&Mat3x3::identity(), offset),
+            Mat4x4::from_affine(&Mat3x3::identity(), -offset),
+        )
+    }
+
+    pub fn scale(factors: Vec3) -> Self {
+        assert!(
+            factors.into_iter().all(|factor| factor != 0.),
+            "Expected non-zero scale factors, got {factors:?}"
+        );
+        let diagonal = |v: Vec3| {
+            Mat3x3::from_row_vectors(
+                Vec3::new(v.x, 0., 0.),
+                Vec3::new(0., v.y, 0.),
+                Vec3::new(0., 0., v.z),
+            )
+        };
+        let inverse = Vec3::new(factors.x.recip(), factors.y.recip(), factors.z.recip());
+        Self::from_parts(
+            Mat4x4::from_affine(&diagonal(factors), Vec3::ZERO),
+            Mat4x4::from_affine(&diagonal(inverse), Vec3::ZERO),
+        )
+    }
+
+    // scales, then rotates, then translates
+    pub fn from_trs(translation: Vec3, rotation: Quaternion, scale: Vec3) -> Self {
+        &(&Self::translation(translation) * &Self::rotation(rotation)) * &Self::scale(scale)
+    }
+
+    pub fn rotation(rotation: Quaternion) -> Self {
+        let matrix = rotation.to_matrix();
+        Self::from_parts(
+            Mat4x4::from_affine(&matrix, Vec3::ZERO),
+            Mat4x4::from_affine(&matrix.transpose(), Vec3::ZERO),
+        )
+    }
+
+    // places the origin at eye looking down -z towards target with +y towards up
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        let forward = (target - eye).normalize();
+        let right = forward.cross(up).normalize();
+        let up = right.cross(forward);
+        let rotation = Mat3x3::from_col_vectors(right, up, -forward);
+        let inverse_rotation = rotation.transpose();
+        let inverse_translation = -(&inverse_rotation * eye);
+        Self::from_parts(
+            Mat4x4::from_affine(&rotation, eye),
+            Mat4x4::from_affine(&inverse_rotation, inverse_translation),
+        )
+    }
+
+    pub fn matrix(&self) -> &Mat4x4 {
+        &self.matrix
+    }
+
+    pub fn inverse_matrix(&self) -> &Mat4x4 {
+        &self.inverse
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self::from_parts(self.inverse.clone(), self.matrix.clone())
+    }
+
+    // applies self and then next
+    pub fn then(&self, next: &Transform) -> Self {
+        next * self
+    }
+
+    pub fn apply_point(&self, point: Vec3) -> Vec3 {
+        self.matrix.transform_point(point)
+    }
+
+    pub fn apply_vector(&self, vector: Vec3) -> Vec3 {
+        self.matrix.transform_vector(vector)
+    }
+
+    // not normalized
+    pub fn apply_normal(&self, normal: Vec3) -> Vec3 {
+        &self.normal_matrix * normal
+    }
+
+    // the direction keeps the scale of the transform, so distances along it are preserved
+    pub fn apply_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(self.apply_point(ray.start), self.apply_vector(ray.dir)).with_time(ray.time)
+    }
+}
+
+/// Remaps the progress between two keyframes
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    // holds the value until the next keyframe
+    Step,
+    // css style timing curve from (0, 0) to (1, 1), x1 and x2 must be in [0, 1]
+    CubicBezier {
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+    },
+}
+
+impl Easing {
+    pub const EASE_IN: Easing = Easing::CubicBezier {
+        x1: 0.42,
+        y1: 0.,
+        x2: 1.,
+        y2: 1.,
+    };
+    pub const EASE_OUT: Easing = Easing::CubicBezier {
+        x1: 0.,
+        y1: 0.,
+        x2: 0.58,
+        y2: 1.,
+    };
+    pub const EASE_IN_OUT: Easing = Easing::CubicBezier {
+        x1: 0.42,
+        y1: 0.,
+        x2: 0.58,
+        y2: 1.,
+    };
+
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0., 1.);
+        match *self {
+            Easing::Linear => t,
+            Easing::Step => (t >= 1.) as u8 as f64,
+            Easing::CubicBezier { x1, y1, x2, y2 } => {
+                let bezier = |a: f64, b: f64, s: f64| {
+                    3. * (1. - s) * (1. - s) * s * a + 3. * (1. - s) * s * s * b + s * s * s
+                };
+                // x is monotonic, so bisect for the curve parameter
+                let (mut low, mut high) = (0., 1.);
+                for _ in 0..48 {
+                    let mid = (low + high) / 2.;
+                    if bezier(x1, x2, mid) < t {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                bezier(y1, y2, (low + high) / 2.)
+            }
+        }
+    }
+
+    // the values apply can return, curves overshoot when y1 or y2 is outside of [0, 1]
+    pub fn range(&self) -> (f64, f64) {
+        match *self {
+            Easing::Linear | Easing::Step => (0., 1.),
+            Easing::CubicBezier { y1, y2, .. } => (y1.min(y2).min(0.), y1.max(y2).max(1.)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+    // towards the next keyframe
+    pub easing: Easing,
+}
+
+impl Keyframe {
+    pub const fn new(time: f64, translation: Vec3, rotation: Quaternion, scale: Vec3) -> Self {
+        Self {
+            time,
+            translation,
+            rotation,
+            scale,
+            easing: Easing::Linear,
+        }
+    }
+
+    pub const fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::from_trs(self.translation, self.rotation, self.scale)
+    }
+}
+
+/// Interpolates decomposed keyframes so rotations don't shear in between
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimatedTransform {
+    keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "Expected at least one keyframe");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { keyframes }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    // holds the first and last keyframes outside of their range
+    pub fn keyframe_at(&self, time: f64) -> Keyframe {
+        let next = self.keyframes.partition_point(|key| key.time <= time);
+        if next == 0 {
+            return self.keyframes[0];
+        }
+        if next == self.keyframes.len() {
+            return self.keyframes[next - 1];
+        }
+        let (a, b) = (self.keyframes[next - 1], self.keyframes[next]);
+        let t = a.easing.apply((time - a.time) / (b.time - a.time));
+        Keyframe::new(
+            time,
+            a.translation * (1. - t) + b.translation * t,
+            a.rotation.slerp(b.rotation, t),
+            a.scale * (1. - t) + b.scale * t,
+        )
+    }
+
+    pub fn at(&self, time: f64) -> Transform {
+        self.keyframe_at(time).transform()
+    }
+
+    // the box around local over the whole motion. Translation and scale move each point along
+    // a line, rotations are covered by the sphere around the local origin they turn about
+    pub fn bounds(&self, local: &Aabb) -> Aabb {
+        let first = local.transform(&self.keyframes[0].transform());
+        let radius = local
+            .corners()
+            .into_iter()
+            .map(Vec3::magnitude)
+            .fold(0., f64::max);
+        self.keyframes.windows(2).fold(first, |bounds, pair| {
+            let (a, b) = (pair[0], pair[1]);
+            let (low, high) = a.easing.range();
+            let [start, end] = [low, high].map(|t| {
+                Keyframe::new(
+                    a.time,
+                    a.translation * (1. - t) + b.translation * t,
+                    a.rotation,
+                    a.scale * (1. - t) + b.scale * t,
+                )
+            });
+            let segment = if a.rotation == b.rotation {
+                local
+                    .transform(&start.transform())
+                    .union(&local.transform(&end.transform()))
+            } else {
+                let scale = [start.scale, end.scale]
+                    .into_iter()
+                    .flat_map(|scale| [scale.x, scale.y, scale.z])
+                    .fold(0., |max: f64, factor| max.max(factor.abs()));
+                let reach = Vec3::splat(radius * scale);
+                let path = Aabb::from_points([start.translation, end.translation]).unwrap();
+                Aabb::new(path.min - reach, path.max + reach)
+            };
+            bounds.union(&segment)
+        })
+    }
+}
+
+impl Aabb {
+    // the box around the transformed corners
+    pub fn transform(&self, transform: &Transform) -> Aabb {
+        Aabb::from_points(self.corners().map(|corner| transform.apply_point(corner))).unwrap()
+    }
+}
+
+impl Mul<&Transform> for &Transform {
+    type Output = Transform;
+
+    // applies rhs and then self
+    fn mul(self, rhs: &Transform) -> Self::Output {
+        Transform::from_parts(&self.matrix * &rhs.matrix, &rhs.inverse * &self.inverse)
+    }
+}
+
+/// Real roots of a x^2 + b x + c in ascending order, degrades to the linear case when a is zero
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a.abs() < 1e-12 {
+        if b == 0. {
+            return None;
+        }
+        let root = -c / b;
+        return Some((root, root));
+    }
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return None;
+    }
+    // avoids cancellation between b and the square root
+    let q = -0.5 * (b + discriminant.sqrt().copysign(b));
+    if q == 0. {
+        return Some((0., 0.));
+    }
+    let (r0, r1) = (q / a, c / q);
+    Some((r0.min(r1), r0.max(r1)))
+}
+
+// the largest real root of x^3 + a x^2 + b x + c
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    let p = b - a * a / 3.;
+    let q = 2. * a * a * a / 27. - a * b / 3. + c;
+    let discriminant = q * q / 4. + p * p * p / 27.;
+    let depressed = if discriminant >= 0. {
+        let root = discriminant.sqrt();
+        (-q / 2. + root).cbrt() + (-q / 2. - root).cbrt()
+    } else {
+        let radius = (-p / 3.).sqrt();
+        let angle = (-q / (2. * radius * radius * radius)).clamp(-1., 1.).acos() / 3.;
+        2. * radius * angle.cos()
+    };
+    depressed - a / 3.
+}
+
+/// Real roots of x^4 + a x^3 + b x^2 + c x + d in ascending order, using Ferrari's method
+/// followed by a few Newton steps on the original polynomial
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    // substituting x = y - a / 4 gives y^4 + p y^2 + q y + r
+    let shift = a / 4.;
+    let p = b - 6. * shift * shift;
+    let q = c - 2. * b * shift + 8. * shift * shift * shift;
+    let r = d - c * shift + b * shift * shift - 3. * shift * shift * shift * shift;
+    let mut roots = Vec::with_capacity(4);
+    let mut push_quadratic = |b: f64, c: f64| {
+        if let Some((r0, r1)) = solve_quadratic(1., b, c) {
+            roots.extend([r0, r1]);
+        }
+    };
+    if q.abs() < 1e-12 {
+        // biquadratic
+        if let Some((z0, z1)) = solve_quadratic(1., p, r) {
+            for z in [z0, z1].into_iter().filter(|&z| z >= 0.) {
+                push_quadratic(0., -z);
+            }
+        }
+    } else {
+        // the resolvent has a positive root since it is -q^2 at zero
+        let m = largest_cubic_root(p, p * p / 4. - r, -q * q / 8.).max(1e-12);
+        let s = (2. * m).sqrt();
+        push_quadratic(-s, p / 2. + m + q / (2. * s));
+        push_quadratic(s, p / 2. + m - q / (2. * s));
+    }
+    for root in &mut roots {
+        let mut x = *root - shift;
+        for _ in 0..3 {
+            let value = (((x + a) * x + b) * x + c) * x + d;
+            let slope = ((4. * x + 3. * a) * x + 2. * b) * x + c;
+            if slope == 0. {
+                break;
+            }
+            x -= value / slope;
+        }
+        *root = x;
+    }
+    roots.sort_by(f64::total_cmp);
+    roots
+}
+
+#[cfg(test)]
+pub(crate) fn assert_close(a: Vec3, b: Vec3) {
+    assert_within(a, b, 1e-9);
+}
+
+#[cfg(test)]
+pub(crate) fn assert_within(a: Vec3, b: Vec3, tolerance: f64) {
+    assert!((a - b).l1_norm() < tolerance, "{a:?} != {b:?}");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::{
+        assert_close, solve_quartic, AnimatedTransform, Easing, Keyframe, Mat3x3, Mat4x4,
+        Quaternion, Transform, Vec3,
+    };
+
+    fn assert_identity(m: &Mat4x4) {
+        for (i, row) in m.rows.iter().enumerate() {
+            for (j, &value) in row.iter().enumerate() {
+                let expected = if i == j { 1. } else { 0. };
+                assert!((value - expected).abs() < 1e-12, "{m:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_mat4x4_inverse() {
+        let m = Mat4x4::from_rows([
+            [0., 2., -1., 3.],
+            [4., 0.5, 2., -1.],
+            [1., -3., 0., 2.],
+            [0.25, 1., 1., 1.],
+        ]);
+        let inverse = m.inverse().unwrap();
+        assert_identity(&(&m * &inverse));
+        assert_identity(&(&inverse * &m));
+        let singular = Mat4x4::from_affine(&Mat3x3::identity().scale(0.), Vec3::X);
+        assert!(singular.inverse().is_none());
+    }
+
+    #[test]
+    fn test_quaternion_rotation() {
+        let q = Quaternion::from_axis_angle(Vec3::Z, FRAC_PI_2);
+        assert_close(q.rotate(Vec3::X), Vec3::Y);
+        assert_close(&q.to_matrix() * Vec3::X, Vec3::Y);
+        let r = Quaternion::from_axis_angle(Vec3::X, FRAC_PI_2);
+        assert_close((r * q).rotate(Vec3::X), r.rotate(q.rotate(Vec3::X)));
+        let half = Quaternion::IDENTITY.slerp(q, 0.5);
+        assert_close(half.rotate(Vec3::X), Vec3::new(1., 1., 0.).normalize());
+    }
+
+    #[test]
+    fn test_transform_compose_and_inverse() {
+        let transform = &(&Transform::translation(Vec3::new(1., 2., 3.))
+            * &Transform::rotation(Quaternion::from_axis_angle(Vec3::Y, 0.7)))
+            * &Transform::scale(Vec3::new(2., 0.5, 3.));
+        let point = Vec3::new(-0.3, 4., 1.5);
+        assert_close(
+            transform
+                .inverse()
+                .apply_point(transform.apply_point(point)),
+            point,
+        );
+        assert_identity(&(transform.matrix() * transform.inverse_matrix()));
+        let general = Transform::from_matrix(transform.matrix().clone()).unwrap();
+        assert_identity(&(general.inverse_matrix() * transform.matrix()));
+        let first = Transform::translation(Vec3::X);
+        let second = Transform::scale(Vec3::splat(2.));
+        assert_close(
+            first.then(&second).apply_point(Vec3::ZERO),
+            Vec3::new(2., 0., 0.),
+        );
+        assert_close(
+            first.then(&second).apply_vector(Vec3::Y),
+            Vec3::new(0., 2., 0.),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "non-zero scale")]
+    fn test_flattening_scale() {
+        Transform::scale(Vec3::new(1., 0., 1.));
+    }
+
+    #[test]
+    fn test_normals_stay_perpendicular() {
+        let transform = &Transform::rotation(Quaternion::from_axis_angle(Vec3::X, 0.3))
+            * &Transform::scale(Vec3::new(1., 5., 0.2));
+        let (tangent, normal) = (Vec3::new(1., -1., 0.), Vec3::new(1., 1., 1.));
+        assert!(
+            transform
+                .apply_vector(tangent)
+                .dot(transform.apply_normal(normal))
+                .abs()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn test_animated_transform() {
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::new(1., Vec3::splat(2.), Quaternion::IDENTITY, Vec3::splat(1.)),
+            Keyframe::new(
+                0.,
+                Vec3::ZERO,
+                Quaternion::from_axis_angle(Vec3::Z, FRAC_PI_2),
+                Vec3::splat(3.),
+            ),
+        ]);
+        assert_close(
+            animation.at(-1.).apply_vector(Vec3::X),
+            Vec3::new(0., 3., 0.),
+        );
+        assert_close(animation.at(2.).apply_point(Vec3::ZERO), Vec3::splat(2.));
+        let half = animation.at(0.5);
+        assert_close(half.apply_point(Vec3::ZERO), Vec3::splat(1.));
+        assert_close(
+            half.apply_vector(Vec3::X),
+            Vec3::new(1., 1., 0.).normalize() * 2.,
+        );
+    }
+
+    #[test]
+    fn test_easing() {
+        for easing in [
+            Easing::Linear,
+            Easing::EASE_IN,
+            Easing::EASE_OUT,
+            Easing::EASE_IN_OUT,
+        ] {
+            assert!(easing.apply(0.).abs() < 1e-9);
+            assert!((easing.apply(1.) - 1.).abs() < 1e-9);
+        }
+        assert!((Easing::EASE_IN_OUT.apply(0.5) - 0.5).abs() < 1e-9);
+        assert!(Easing::EASE_IN.apply(0.25) < 0.25);
+        assert!(Easing::EASE_OUT.apply(0.25) > 0.25);
+        assert_eq!(Easing::Step.apply(0.99), 0.);
+    }
+
+    #[test]
+    fn test_look_at() {
+        let (eye, target) = (Vec3::new(1., 2., 3.), Vec3::new(-2., 0., 1.));
+        let transform = Transform::look_at(eye, target, Vec3::Y);
+        assert_close(transform.apply_point(Vec3::ZERO), eye);
+        assert_close(
+            transform.apply_vector(Vec3::NEG_Z),
+            (target - eye).normalize(),
+        );
+        assert!(transform.apply_vector(Vec3::Y).y > 0.);
+        assert_close(transform.inverse().apply_point(eye), Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_solve_quartic() {
+        let expand = |roots: [f64; 4]| {
+            let [r0, r1, r2, r3] = roots;
+            (
+                -(r0 + r1 + r2 + r3),
+                r0 * r1 + r0 * r2 + r0 * r3 + r1 * r2 + r1 * r3 + r2 * r3,
+                -(r0 * r1 * r2 + r0 * r1 * r3 + r0 * r2 * r3 + r1 * r2 * r3),
+                r0 * r1 * r2 * r3,
+            )
+        };
+        for expected in [[-3., -1., 0.5, 2.], [1., 2., 3., 4.], [-2., -2., 2., 2.]] {
+            let (a, b, c, d) = expand(expected);
+            let roots = solve_quartic(a, b, c, d);
+            assert_eq!(roots.len(), 4, "{expected:?}");
+            for (root, expected) in roots.iter().zip(expected) {
+                assert!((root - expected).abs() < 1e-6, "{roots:?} != {expected:?}");
+            }
+        }
+        // (x^2 + 1)(x^2 - 4)
+        let roots = solve_quartic(0., -3., 0., -4.);
+        assert_eq!(roots.len(), 2);
+        assert!((roots[0] + 2.).abs() < 1e-9 && (roots[1] - 2.).abs() < 1e-9);
+        assert!(solve_quartic(0., 2., 0., 1.).is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    f64::consts::{PI, TAU},
+    fmt::Debug,
+};
+
+use crate::{
+    math::{Ray, Vec2, Vec3},
+    scene::Camera,
+};
+
+/// Maps a point on the film to a ray leaving the camera
+pub trait Projection: Debug {
+    // film is in [-0.5, 0.5]^2 with +y up, aspect is the film width over its height,
+    // lens_sample in [0, 1)^2, no ray for film points outside of the projection
+    fn ray(&self, camera: &Camera, film: Vec2, aspect: f64, lens_sample: Vec2) -> Option<Ray>;
+}
+
+// uses the field of view and aperture of the camera
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Perspective;
+
+impl Projection for Perspective {
+    fn ray(&self, camera: &Camera, film: Vec2, _aspect: f64, lens_sample: Vec2) -> Option<Ray> {
+        let dir = camera.forward
+            + film.x * -camera.max_left_deflection()
+            + film.y * camera.max_up_deflection();
+        if camera.aperture_radius <= 0. {
+            return Some(Ray::new_unit(camera.pos, dir));
+        }
+        let focus_point = camera.pos + dir * (camera.focus_distance / dir.dot(camera.forward));
+        let lens = camera.sample_aperture(lens_sample) * camera.aperture_radius;
+        let (right, up) = camera.basis();
+        let start = camera.pos + right * lens.x + up * lens.y;
+        Some(Ray::new_unit(start, focus_point - start))
+    }
+}
+
+// parallel rays, height is the visible extent in world units
+#[derive(Debug, Clone, Copy)]
+pub struct Orthographic {
+    pub height: f64,
+}
+
+impl Orthographic {
+    pub const fn new(height: f64) -> Self {
+        Self { height }
+    }
+}
+
+impl Projection for Orthographic {
+    fn ray(&self, camera: &Camera, film: Vec2, aspect: f64, _lens_sample: Vec2) -> Option<Ray> {
+        let (right, up) = camera.basis();
+        let start = camera.pos + (right * (film.x * aspect) + up * film.y) * self.height;
+        Some(Ray::new(start, camera.forward))
+    }
+}
+
+// equidistant, the angle from the forward axis grows linearly with the distance from the center,
+// fov is the full angle in degrees across the image circle fitting the shorter side
+#[derive(Debug, Clone, Copy)]
+pub struct Fisheye {
+    pub fov: f64,
+}
+
+impl Fisheye {
+    pub const fn new(fov: f64) -> Self {
+        Self { fov }
+    }
+}
+
+impl Projection for Fisheye {
+    fn ray(&self, camera: &Camera, film: Vec2, aspect: f64, _lens_sample: Vec2) -> Option<Ray> {
+        let circle = Vec2::new(film.x * aspect.max(1.), film.y * aspect.recip().max(1.)) * 2.;
+        let radius = circle.dot(circle).sqrt();
+        if radius > 1. {
+            return None;
+        }
+        let (sin, cos) = (radius * self.fov.to_radians() / 2.).sin_cos();
+        let (right, up) = camera.basis();
+        let sideways = if radius > 0. {
+            (right * circle.x + up * circle.y) / radius
+        } else {
+            Vec3::ZERO
+        };
+        Some(Ray::new_unit(
+            camera.pos,
+            camera.forward * cos + sideways * sin,
+        ))
+    }
+}
+
+// latitude and longitude over the whole sphere, forward is at the center of the image
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Equirectangular;
+
+impl Projection for Equirectangular {
+    fn ray(&self, camera: &Camera, film: Vec2, _aspect: f64, _lens_sample: Vec2) -> Option<Ray> {
+        let (right, up) = camera.basis();
+        let (sin_lon, cos_lon) = (film.x * TAU).sin_cos();
+        let (sin_lat, cos_lat) = (film.y * PI).sin_cos();
+        let dir = (camera.forward * cos_lon + right * sin_lon) * cos_lat + up * sin_lat;
+        Some(Ray::new_unit(camera.pos, dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::{assert_close, Vec2, Vec3},
+        scene::Camera,
+    };
+
+    use super::{Equirectangular, Fisheye, Orthographic, Projection};
+
+    fn camera() -> Camera {
+        Camera::new(30., 30., Vec3::new(1., 2., 3.), Vec3::NEG_Z, Vec3::Y)
+    }
+
+    #[test]
+    fn test_orthographic_rays_are_parallel() {
+        let camera = camera();
+        let ray = Orthographic::new(4.)
+            .ray(&camera, Vec2::new(0.5, -0.5), 2., Vec2::ZERO)
+            .unwrap();
+        assert_close(ray.dir, Vec3::NEG_Z);
+        assert_close(ray.start, camera.pos + Vec3::new(4., -2., 0.));
+    }
+
+    #[test]
+    fn test_fisheye_is_equidistant() {
+        let camera = camera();
+        let fisheye = Fisheye::new(180.);
+        let edge = fisheye
+            .ray(&camera, Vec2::new(0., 0.5), 1., Vec2::ZERO)
+            .unwrap();
+        assert_close(edge.dir, Vec3::Y);
+        let halfway = fisheye
+            .ray(&camera, Vec2::new(0.25, 0.), 1., Vec2::ZERO)
+            .unwrap();
+        assert!((halfway.dir.angle_to(Vec3::NEG_Z) - 45f64.to_radians()).abs() < 1e-9);
+        assert!(fisheye
+            .ray(&camera, Vec2::new(0.5, 0.5), 1., Vec2::ZERO)
+            .is_none());
+    }
+
+    #[test]
+    fn test_equirectangular_covers_the_sphere() {
+        let camera = camera();
+        let ray = |x, y| {
+            Equirectangular
+                .ray(&camera, Vec2::new(x, y), 2., Vec2::ZERO)
+                .unwrap()
+                .dir
+        };
+        assert_close(ray(0., 0.), Vec3::NEG_Z);
+        assert_close(ray(0.25, 0.), Vec3::X);
+        assert_close(ray(0.5, 0.), Vec3::Z);
+        assert_close(ray(0.1, 0.5), Vec3::Y);
+    }
+}
@@ -1,8 +1,12 @@
+use std::{f64::consts::TAU, ops::Range, sync::Arc};
+
 use crate::{
     img::Color,
     material::Material,
-    math::{Ray, Vec3},
-    shapes::Shape,
+    math::{Aabb, Quaternion, Ray, Vec2, Vec3},
+    projection::{Perspective, Projection},
+    shapes::{RayCollision, Shape},
+    spectrum::{SampledSpectrum, SampledWavelengths},
     EPSILON,
 };
 
@@ -39,6 +43,52 @@ impl IntoIterator for Display {
     }
 }
 
+/// A rectangle of pixels in the coordinates of the renderer, with y going up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub x: Range<u32>,
+    pub y: Range<u32>,
+}
+
+impl Region {
+    pub const fn new(x: Range<u32>, y: Range<u32>) -> Self {
+        Self { x, y }
+    }
+
+    pub const fn full(display: Display) -> Self {
+        Self::new(0..display.x, 0..display.y)
+    }
+
+    // measured from the top left corner, as in an image viewer
+    pub const fn from_top_left(
+        display: Display,
+        left: u32,
+        top: u32,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let bottom = display.y.saturating_sub(top + height);
+        Self::new(left..left + width, bottom..display.y.saturating_sub(top))
+    }
+
+    pub fn clamp(&self, display: Display) -> Self {
+        let clamp = |range: &Range<u32>, len: u32| range.start.min(len)..range.end.min(len);
+        Self::new(clamp(&self.x, display.x), clamp(&self.y, display.y))
+    }
+
+    pub fn size(&self) -> Display {
+        Display::new(self.x.len() as u32, self.y.len() as u32)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty() || self.y.is_empty()
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        self.x.contains(&x) && self.y.contains(&y)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct DisplayIter {
     display: Display,
@@ -121,23 +171,43 @@ impl DoubleEndedIterator for DisplayIter {
 
 #[derive(Debug)]
 pub struct Camera {
+    // angles in degrees from the forward axis to the edges of the image
     pub xfov: f64,
     pub yfov: f64,
     pub pos: Vec3,
     pub forward: Vec3,
     pub up: Vec3,
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    // a zero radius is a pinhole with everything in focus
+    pub aperture_radius: f64,
+    // along forward, not along the ray
+    pub focus_distance: f64,
+    // a round aperture below 3 blades
+    pub aperture_blades: u32,
+    pub blade_rotation: f64,
+    pub projection: Arc<dyn Projection + Send + Sync>,
     max_left: Vec3,
     max_up: Vec3,
 }
 
 impl Camera {
+    pub const FULL_FRAME_WIDTH: f64 = 36.;
+
     pub fn new(xfov: f64, yfov: f64, pos: Vec3, forward: Vec3, up: Vec3) -> Self {
         let mut cam = Self {
             xfov,
             yfov,
             pos,
             forward: forward.normalize(),
-            up: up.normalize(),
+            up: up.reject_wrt(forward).normalize(),
+            shutter_open: 0.,
+            shutter_close: 0.,
+            aperture_radius: 0.,
+            focus_distance: 1.,
+            aperture_blades: 0,
+            blade_rotation: 0.,
+            projection: Arc::new(Perspective),
             max_left: Vec3::ZERO,
             max_up: Vec3::ZERO,
         };
@@ -146,29 +216,150 @@ impl Camera {
         cam
     }
 
+    // assumes square pixels
     pub fn from_display(xfov: f64, display: Display, pos: Vec3, forward: Vec3, up: Vec3) -> Self {
-        Self::new(
-            xfov,
-            (display.y as f64 / display.x as f64) * xfov,
-            pos,
-            forward,
+        Self::from_aspect(xfov, display.x as f64 / display.y as f64, pos, forward, up)
+    }
+
+    // aspect is the width of the image over its height, which for non square pixels is
+    // the ratio of the display times the width of a pixel over its height
+    pub fn from_aspect(xfov: f64, aspect: f64, pos: Vec3, forward: Vec3, up: Vec3) -> Self {
+        let yfov = (xfov.to_radians().tan() / aspect).atan().to_degrees();
+        Self::new(xfov, yfov, pos, forward, up)
+    }
+
+    // yfov as in new
+    pub fn look_at(pos: Vec3, target: Vec3, up: Vec3, yfov: f64, aspect: f64) -> Self {
+        let xfov = (yfov.to_radians().tan() * aspect).atan().to_degrees();
+        Self::new(xfov, yfov, pos, target - pos, up)
+    }
+
+    // focal length and sensor width in mm, the sensor height follows from aspect
+    pub fn from_focal_length(
+        focal_length: f64,
+        sensor_width: f64,
+        aspect: f64,
+        pos: Vec3,
+        forward: Vec3,
+        up: Vec3,
+    ) -> Self {
+        let xfov = (sensor_width / 2. / focal_length).atan().to_degrees();
+        Self::from_aspect(xfov, aspect, pos, forward, up)
+    }
+
+    // backs away along view_dir until the sphere around bounds fits into the image, yfov as in new
+    pub fn framing(bounds: &Aabb, view_dir: Vec3, up: Vec3, yfov: f64, aspect: f64) -> Self {
+        let xfov = (yfov.to_radians().tan() * aspect).atan();
+        let half_angle = xfov.min(yfov.to_radians());
+        let distance = bounds.bounding_radius() / half_angle.sin();
+        let center = bounds.center();
+        Self::look_at(
+            center - view_dir.normalize() * distance,
+            center,
             up,
+            yfov,
+            aspect,
         )
     }
 
+    // count framing cameras evenly spaced around up, starting on the +z side (or +x if up is z),
+    // elevation in degrees above the plane perpendicular to up
+    pub fn turntable(
+        bounds: &Aabb,
+        count: usize,
+        elevation: f64,
+        up: Vec3,
+        yfov: f64,
+        aspect: f64,
+    ) -> Vec<Self> {
+        let up = up.normalize();
+        let side = if up.cross(Vec3::Z) == Vec3::ZERO {
+            Vec3::X
+        } else {
+            Vec3::Z.reject_wrt(up).normalize()
+        };
+        let (sin, cos) = elevation.to_radians().sin_cos();
+        (0..count)
+            .map(|i| {
+                let turn = Quaternion::from_axis_angle(up, TAU * i as f64 / count as f64);
+                let offset = turn.rotate(side) * cos + up * sin;
+                Self::framing(bounds, -offset, up, yfov, aspect)
+            })
+            .collect()
+    }
+
+    pub fn focal_length(&self, sensor_width: f64) -> f64 {
+        sensor_width / 2. / self.xfov.to_radians().tan()
+    }
+
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    // u in [0, 1)
+    pub fn sample_time(&self, u: f64) -> f64 {
+        self.shutter_open + (self.shutter_close - self.shutter_open) * u
+    }
+
+    pub fn with_aperture(mut self, radius: f64, focus_distance: f64) -> Self {
+        self.aperture_radius = radius;
+        self.focus_distance = focus_distance;
+        self
+    }
+
+    // rotation in degrees
+    pub fn with_blades(mut self, blades: u32, rotation: f64) -> Self {
+        self.aperture_blades = blades;
+        self.blade_rotation = rotation;
+        self
+    }
+
+    pub fn with_projection(mut self, projection: impl Projection + Send + Sync + 'static) -> Self {
+        self.projection = Arc::new(projection);
+        self
+    }
+
+    // orthonormal right and up, up may not be perpendicular to forward
+    pub fn basis(&self) -> (Vec3, Vec3) {
+        let right = self.forward.cross(self.up).normalize();
+        (right, right.cross(self.forward))
+    }
+
+    // uniformly samples the aperture shape with a radius of 1, u in [0, 1)^2
+    pub fn sample_aperture(&self, u: Vec2) -> Vec2 {
+        if self.aperture_blades < 3 {
+            let (sin, cos) = (u.y * TAU).sin_cos();
+            return Vec2::new(cos, sin) * u.x.sqrt();
+        }
+        // pick one of the triangles between the center and two adjacent blade tips
+        let blades = self.aperture_blades as f64;
+        let scaled = u.x * blades;
+        let triangle = scaled.floor();
+        let (a, b) = (scaled - triangle, u.y);
+        let (a, b) = if a + b > 1. { (1. - a, 1. - b) } else { (a, b) };
+        let corner = |i: f64| {
+            let (sin, cos) = (i / blades * TAU + self.blade_rotation.to_radians()).sin_cos();
+            Vec2::new(cos, sin)
+        };
+        corner(triangle) * a + corner(triangle + 1.) * b
+    }
+
     pub fn left(&self) -> Vec3 {
         self.up.cross(self.forward)
     }
 
+    // spans the whole image, from the right edge to the left edge
     fn calc_max_left(&self) -> Vec3 {
         let (facing_left_x, facing_left_z) = self.xfov.to_radians().sin_cos();
-        let max_deflection = facing_left_x / facing_left_z;
+        let max_deflection = 2. * facing_left_x / facing_left_z;
         max_deflection * self.left()
     }
 
     fn calc_max_up(&self) -> Vec3 {
         let (facing_top_y, facing_top_z) = self.yfov.to_radians().sin_cos();
-        let up_deflection = facing_top_y / facing_top_z;
+        let up_deflection = 2. * facing_top_y / facing_top_z;
         up_deflection * self.up
     }
 
@@ -181,6 +372,23 @@ impl Camera {
     }
 }
 
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AovSample {
+    pub beauty: Color,
+    pub direct: Color,
+    pub indirect: Color,
+    pub hit: Option<AovHit>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AovHit {
+    pub depth: f64,
+    pub normal: Vec3,
+    pub albedo: Color,
+    pub material_id: u64,
+    pub object_id: usize,
+}
+
 #[derive(Debug)]
 pub struct Scene<S: Shape> {
     pub display: Display,
@@ -197,19 +405,37 @@ impl<S: Shape> Scene<S> {
         ray.dir.dot(to_light_ray_dist).max(0.)
     }
 
-    pub fn pixel_ray(&self, x: f64, y: f64) -> Ray {
-        let x_percent = x / self.display.x as f64 - 0.5;
-        let y_percent = y / self.display.y as f64 - 0.5;
-        let dir = (self.camera.forward
-            + x_percent * -self.camera.max_left_deflection()
-            + y_percent * self.camera.max_up_deflection())
-        .normalize();
-        Ray::new(self.camera.pos, dir)
+    // lens_sample in [0, 1)^2 picks the point on the aperture
+    pub fn pixel_ray(&self, x: f64, y: f64, lens_sample: Vec2) -> Option<Ray> {
+        let film = Vec2::new(
+            x / self.display.x as f64 - 0.5,
+            y / self.display.y as f64 - 0.5,
+        );
+        let aspect = self.display.x as f64 / self.display.y as f64;
+        self.camera
+            .projection
+            .ray(&self.camera, film, aspect, lens_sample)
+    }
+
+    pub fn world_bounds(&self) -> Option<Aabb> {
+        self.world.bounds()
+    }
+
+    // a camera looking along view_dir at the whole world, yfov as in Camera::new
+    pub fn framing_camera(&self, view_dir: Vec3, up: Vec3, yfov: f64) -> Option<Camera> {
+        let aspect = self.display.x as f64 / self.display.y as f64;
+        Some(Camera::framing(
+            &self.world_bounds()?,
+            view_dir,
+            up,
+            yfov,
+            aspect,
+        ))
     }
 
-    pub fn sees_light(&self, pos: Vec3) -> bool {
+    pub fn sees_light(&self, pos: Vec3, time: f64) -> bool {
         let light_relative = self.light_pos - pos;
-        let to_light_ray = Ray::new_unit(pos, light_relative);
+        let to_light_ray = Ray::new_unit(pos, light_relative).with_time(time);
         !self
             .world
             .intersect_inclusive(to_light_ray)
@@ -219,9 +445,13 @@ impl<S: Shape> Scene<S> {
     }
 
     pub fn cast_ray(&self, ray: Ray, bounces: u16) -> Color {
-        let Some(collision) = self.world.intersect_exclusive(ray.clone()) else {
-            return self.background_color;
-        };
+        match self.world.intersect_exclusive(ray) {
+            Some(collision) => self.shade(collision, bounces),
+            None => self.background_color,
+        }
+    }
+
+    fn shade(&self, collision: RayCollision<S::Material>, bounces: u16) -> Color {
         if bounces >= 1 {
             let new_ray = collision
                 .material
@@ -229,12 +459,78 @@ impl<S: Shape> Scene<S> {
             let light_color = self.cast_ray(new_ray, bounces - 1);
             return collision.material.update_color(light_color);
         }
-        let brightness = self.brightness(collision.reflection())
-            * self.sees_light(collision.collision_point()) as i32 as f64
+        let brightness = self.light_intensity(&collision);
+        collision.material.update_color(Color::gray(brightness))
+    }
+
+    pub fn cast_ray_spectral(
+        &self,
+        ray: Ray,
+        bounces: u16,
+        lambdas: &mut SampledWavelengths,
+    ) -> SampledSpectrum {
+        let Some(collision) = self.world.intersect_exclusive(ray.clone()) else {
+            return SampledSpectrum::from_rgb(self.background_color, lambdas);
+        };
+        if bounces >= 1 {
+            let new_ray = collision
+                .material
+                .update_ray_spectral(collision.ray.translate(collision.distance), lambdas);
+            let light = self.cast_ray_spectral(new_ray, bounces - 1, lambdas);
+            return collision.material.update_spectrum(light, lambdas);
+        }
+        let brightness = self.light_intensity(&collision);
+        collision
+            .material
+            .update_spectrum(SampledSpectrum::splat(brightness), lambdas)
+    }
+
+    // direct light is whatever reaches the first hit without bouncing off anything else
+    pub fn cast_ray_aov(&self, ray: Ray, bounces: u16) -> AovSample {
+        let Some(collision) = self.world.intersect_exclusive(ray.clone()) else {
+            return AovSample {
+                beauty: self.background_color,
+                direct: self.background_color,
+                ..Default::default()
+            };
+        };
+        let hit = Some(AovHit {
+            depth: collision.distance,
+            normal: collision.shading_normal(),
+            albedo: collision.material.albedo(),
+            material_id: collision.material.id(),
+            object_id: collision.object_id,
+        });
+        let (direct, indirect) = if bounces >= 1 {
+            match self.world.intersect_exclusive(collision.reflection()) {
+                Some(next) => {
+                    let light = self.shade(next, bounces - 1);
+                    (Color::BLACK, collision.material.update_color(light))
+                }
+                None => {
+                    let light = self.background_color;
+                    (collision.material.update_color(light), Color::BLACK)
+                }
+            }
+        } else {
+            let brightness = self.light_intensity(&collision);
+            let light = collision.material.update_color(Color::gray(brightness));
+            (light, Color::BLACK)
+        };
+        AovSample {
+            beauty: Color(direct.0 + indirect.0),
+            direct,
+            indirect,
+            hit,
+        }
+    }
+
+    fn light_intensity(&self, collision: &RayCollision<S::Material>) -> f64 {
+        self.brightness(collision.reflection())
+            * self.sees_light(collision.collision_point(), collision.ray.time) as i32 as f64
             * (collision.collision_point() - self.light_pos)
                 .magnitude()
-                .powi(-2);
-        collision.material.update_color(Color::gray(brightness))
+                .powi(-2)
     }
 
     pub fn num_bounces(&self, ray: Ray, max_bounces: u16) -> u16 {
@@ -253,21 +549,33 @@ impl<S: Shape> Scene<S> {
 
 #[cfg(test)]
 mod tests {
-    use super::Display;
+    use std::f64::consts::PI;
+
+    use crate::{
+        film::{Aov, AovFilm, Film, Filter},
+        img::{Color, ColorSpace, Image},
+        material::Lambertian,
+        math::{Aabb, Vec2, Vec3},
+        projection::{Perspective, Projection},
+        shapes::Sphere,
+        spectrum::SampledWavelengths,
+    };
+
+    use super::{Camera, Display, Region, Scene};
 
     #[test]
     fn test_iter_len_head_and_tail() {
         let display = Display { x: 12, y: 7 };
         let mut it = display.into_iter();
         for _ in 0..50 {
-            if it.len() != it.clone().count() {
+            if it.len() != it.count() {
                 dbg!(it);
-                assert_eq!(it.len(), it.clone().count());
+                assert_eq!(it.len(), it.count());
             }
             let _ = it.next();
-            if it.len() != it.clone().count() {
+            if it.len() != it.count() {
                 dbg!(it);
-                assert_eq!(it.len(), it.clone().count());
+                assert_eq!(it.len(), it.count());
             }
             let _ = it.next_back();
         }
@@ -278,7 +586,7 @@ mod tests {
         let display = Display { x: 12, y: 7 };
         let mut it = display.into_iter();
         for _ in 0..100 {
-            assert_eq!(it.len(), it.clone().count());
+            assert_eq!(it.len(), it.count());
             let _ = it.next();
         }
     }
@@ -304,4 +612,204 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_aperture_samples_stay_inside() {
+        for blades in [0, 3, 6] {
+            let camera =
+                Camera::new(30., 30., Vec3::ZERO, Vec3::NEG_Z, Vec3::Y).with_blades(blades, 15.);
+            let tips: Vec<Vec2> = (0..blades)
+                .map(|i| {
+                    let angle = i as f64 / blades as f64 * 2. * PI + 15f64.to_radians();
+                    Vec2::new(angle.cos(), angle.sin())
+                })
+                .collect();
+            assert_eq!(camera.sample_aperture(Vec2::ZERO), Vec2::ZERO);
+            for i in 0..32 {
+                for j in 0..32 {
+                    let sample = camera.sample_aperture(Vec2::new(i as f64 / 32., j as f64 / 32.));
+                    assert!(sample.magnitude() <= 1. + 1e-9, "{sample:?}");
+                    // left of every edge of the counterclockwise blade polygon
+                    for (&a, &b) in tips.iter().zip(tips.iter().cycle().skip(1)) {
+                        assert!((b - a).cross(sample - a) >= -1e-9, "{blades} {sample:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_camera_fov() {
+        let camera = Camera::look_at(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y, 45., 16. / 9.);
+        assert!((camera.max_up_deflection() - Vec3::new(0., 2., 0.)).l1_norm() < 1e-9);
+        assert!((camera.max_left_deflection() - Vec3::new(-32. / 9., 0., 0.)).l1_norm() < 1e-9);
+        // a 50mm lens on a full frame sensor sees about 39.6 degrees across
+        let camera = Camera::from_focal_length(
+            50.,
+            Camera::FULL_FRAME_WIDTH,
+            1.5,
+            Vec3::ZERO,
+            Vec3::NEG_Z,
+            Vec3::Y,
+        );
+        assert!((camera.xfov * 2. - 39.6).abs() < 0.1);
+        assert!((camera.yfov * 2. - 27.).abs() < 0.1);
+        assert!((camera.focal_length(Camera::FULL_FRAME_WIDTH) - 50.).abs() < 1e-9);
+        // anamorphic pixels twice as wide as they are tall
+        let display = Display::new(1000, 1000);
+        let square = Camera::from_display(30., display, Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        let wide = Camera::from_aspect(30., 2., Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        assert!((square.yfov - 30.).abs() < 1e-9);
+        assert!(
+            (wide.max_left_deflection().magnitude() - 2. * wide.max_up_deflection().magnitude())
+                .abs()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn test_image_plane_extent() {
+        let display = Display::new(200, 100);
+        let camera = Camera::from_display(30., display, Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        // at unit distance the image spans twice the tangent of the fov each way
+        let width = 2. * 30f64.to_radians().tan();
+        assert!((camera.max_left_deflection() - Vec3::new(-width, 0., 0.)).l1_norm() < 1e-9);
+        assert!((camera.max_up_deflection() - Vec3::new(0., width / 2., 0.)).l1_norm() < 1e-9);
+        let edge = |film: Vec2| {
+            let ray = Perspective.ray(&camera, film, 2., Vec2::ZERO).unwrap();
+            ray.dir.angle_to(camera.forward).to_degrees()
+        };
+        assert!((edge(Vec2::new(0.5, 0.)) - 30.).abs() < 1e-9);
+        assert!((edge(Vec2::new(-0.5, 0.)) - 30.).abs() < 1e-9);
+        assert!((edge(Vec2::new(0., 0.5)) - camera.yfov).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_framing() {
+        let bounds = Aabb::new(Vec3::new(1., 1., 1.), Vec3::new(3., 5., 3.));
+        for (aspect, yfov) in [(16. / 9., 30.), (0.5, 40.)] {
+            let cameras = Camera::turntable(&bounds, 4, 20., Vec3::Y, yfov, aspect);
+            assert_eq!(cameras.len(), 4);
+            for camera in &cameras {
+                let to_center = bounds.center() - camera.pos;
+                assert!((to_center.normalize() - camera.forward).l1_norm() < 1e-9);
+                // every corner is in front of the camera and inside the image
+                for corner in bounds.corners() {
+                    let relative = corner - camera.pos;
+                    let depth = relative.dot(camera.forward);
+                    let (right, up) = camera.basis();
+                    assert!(depth > 0.);
+                    assert!(relative.dot(right).abs() / depth <= camera.xfov.to_radians().tan());
+                    assert!(relative.dot(up).abs() / depth <= camera.yfov.to_radians().tan());
+                }
+            }
+            assert!((cameras[0].pos.x - bounds.center().x).abs() < 1e-9);
+            assert!(cameras[0].pos.z > bounds.center().z);
+            assert!(cameras[0].pos.y > bounds.center().y);
+        }
+    }
+
+    #[test]
+    fn test_region_crop() {
+        let display = Display::new(6, 4);
+        let region = Region::from_top_left(display, 1, 0, 3, 2);
+        assert_eq!(region, Region::new(1..4, 2..4));
+        assert_eq!(
+            Region::new(4..9, 0..2).clamp(display),
+            Region::new(4..6, 0..2)
+        );
+        let mut img = Image::zeros(display);
+        for (x, y) in display {
+            *img.at_mut(x as usize, y as usize) = Color::gray((x + 10 * y) as f64);
+        }
+        let cropped = img.crop(&region);
+        assert_eq!((cropped.width(), cropped.height()), (3, 2));
+        // the top left pixel of the crop is the top left pixel of the region in the image
+        assert_eq!(cropped.at(0, 0), img.at(1, 0));
+        assert_eq!(cropped.at(2, 1), Color::gray(23.));
+    }
+
+    #[test]
+    fn test_aov_pixels() {
+        let display = Display::new(5, 5);
+        let scene = Scene {
+            display,
+            camera: Camera::from_display(30., display, Vec3::ZERO, Vec3::NEG_Z, Vec3::Y),
+            light_pos: Vec3::new(0., 5., 0.),
+            world: Sphere::new(
+                Vec3::new(0., 0., -5.),
+                1.,
+                Color::from_rgb(0.8, 0.2, 0.1),
+                Lambertian,
+            ),
+            background_color: Color::gray(0.5),
+        };
+        let mut film = AovFilm::new(
+            display,
+            &[Aov::Depth, Aov::Normal, Aov::Albedo],
+            Filter::MITCHELL,
+        );
+        for (x, y) in display {
+            let (x, y) = (x as f64 + 0.5, y as f64 + 0.5);
+            let ray = scene.pixel_ray(x, y, Vec2::ZERO).unwrap();
+            film.add_sample(x, y, &scene.cast_ray_aov(ray, 0));
+        }
+        let images = film.to_images();
+        let expected = [
+            (Aov::Depth, Color::gray(4.)),
+            (Aov::Normal, Color::from_rgb(0., 0., 1.)),
+            (Aov::Albedo, Color::from_rgb(0.8, 0.2, 0.1)),
+        ];
+        for ((aov, img), (expected_aov, center)) in images.iter().zip(expected) {
+            assert_eq!(*aov, expected_aov);
+            assert!((img.at(2, 2).0 - center.0).l1_norm() < 1e-9, "{aov:?}");
+            // the corners see the background, which leaves the geometric layers empty
+            assert_eq!(img.at(0, 0), Color::BLACK, "{aov:?}");
+        }
+    }
+
+    #[test]
+    fn test_spectral_matches_rgb() {
+        let display = Display::new(5, 5);
+        let scene = Scene {
+            display,
+            camera: Camera::from_display(30., display, Vec3::ZERO, Vec3::NEG_Z, Vec3::Y),
+            light_pos: Vec3::new(0., 5., 0.),
+            world: Sphere::new(Vec3::new(0., 0., -5.), 1., Color::gray(0.6), Lambertian),
+            background_color: Color::gray(0.5),
+        };
+        // spreading the wavelengths evenly over each pixel averages out their noise
+        const SAMPLES: usize = 500;
+        let (mut rgb, mut spectral) = (
+            Film::new(display, Filter::PIXEL),
+            Film::new(display, Filter::PIXEL),
+        );
+        for (x, y) in display {
+            let (x, y) = (x as f64 + 0.5, y as f64 + 0.5);
+            let ray = scene.pixel_ray(x, y, Vec2::ZERO).unwrap();
+            rgb.add_sample(x, y, scene.cast_ray(ray.clone(), 1));
+            for i in 0..SAMPLES {
+                let mut lambdas =
+                    SampledWavelengths::sample_uniform((i as f64 + 0.5) / SAMPLES as f64);
+                let color = scene
+                    .cast_ray_spectral(ray.clone(), 1, &mut lambdas)
+                    .to_color(&lambdas, ColorSpace::Srgb);
+                spectral.add_sample(x, y, color);
+            }
+        }
+        let (rgb, spectral) = (rgb.to_image(), spectral.to_image());
+        // every bounce off the sphere escapes to the background
+        assert_eq!(rgb.at(2, 2), Color::gray(0.3));
+        assert_eq!(rgb.at(0, 0), Color::gray(0.5));
+        for (x, y) in display {
+            let (expected, actual) = (
+                rgb.at(x as usize, y as usize),
+                spectral.at(x as usize, y as usize),
+            );
+            assert!(
+                (actual.0 - expected.0).l1_norm() < 0.03,
+                "{x} {y}: {actual:?} != {expected:?}"
+            );
+        }
+    }
 }
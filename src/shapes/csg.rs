@@ -0,0 +1,262 @@
+use crate::{
+    material::Material,
+    math::{Aabb, Ray, Vec3},
+};
+
+use super::{Collision, Shape};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    // the first shape with the second cut out of it
+    Difference,
+}
+
+impl CsgOperation {
+    fn contains(self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_a || in_b,
+            CsgOperation::Intersection => in_a && in_b,
+            CsgOperation::Difference => in_a && !in_b,
+        }
+    }
+}
+
+/// Combines two closed shapes whose normals face out, by following the ray in and out of both.
+/// Surfaces of one shape closer than EPSILON along the ray can merge, losing the exit from a
+/// thin shell
+#[derive(Debug, Clone)]
+pub struct Csg<A, B> {
+    pub operation: CsgOperation,
+    pub a: A,
+    pub b: B,
+}
+
+impl<A: Shape, B: Shape<Material = A::Material>> Csg<A, B> {
+    pub fn new(operation: CsgOperation, a: A, b: B) -> Self {
+        assert!(
+            a.is_closed() && b.is_closed(),
+            "Expected closed shapes, open surfaces have no inside to combine"
+        );
+        Self { operation, a, b }
+    }
+
+    pub fn union(a: A, b: B) -> Self {
+        Self::new(CsgOperation::Union, a, b)
+    }
+
+    pub fn intersection(a: A, b: B) -> Self {
+        Self::new(CsgOperation::Intersection, a, b)
+    }
+
+    pub fn difference(a: A, b: B) -> Self {
+        Self::new(CsgOperation::Difference, a, b)
+    }
+}
+
+// the ray starts inside a closed shape when it first leaves it
+fn starts_inside<M: Material>(collisions: &[Collision<M>]) -> bool {
+    collisions
+        .first()
+        .is_some_and(|collision| !collision.front_face)
+}
+
+// the surface of b bounds a difference from the inside of b
+fn flip<M: Material>(mut collision: Collision<M>) -> Collision<M> {
+    collision.normal = -collision.normal;
+    collision.front_face = !collision.front_face;
+    let normal = collision.material.normal();
+    collision.material.set_normal(-normal);
+    collision
+}
+
+impl<A: Shape, B: Shape<Material = A::Material>> Shape for Csg<A, B> {
+    type Material = A::Material;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        self.ray_intersections(ray, include_start)
+            .into_iter()
+            .next()
+    }
+
+    fn ray_intersections(&self, ray: Ray, include_start: bool) -> Vec<Collision<Self::Material>> {
+        let a = self.a.ray_intersections(ray.clone(), include_start);
+        let b = self.b.ray_intersections(ray, include_start);
+        let (mut in_a, mut in_b) = (starts_inside(&a), starts_inside(&b));
+        let mut inside = self.operation.contains(in_a, in_b);
+        let mut events: Vec<_> = a
+            .into_iter()
+            .map(|collision| (collision, false))
+            .chain(b.into_iter().map(|collision| {
+                // the objects of b are numbered after those of a
+                let object_id = self.a.object_count() + collision.object_id;
+                (collision.with_object_id(object_id), true)
+            }))
+            .collect();
+        events.sort_by(|(c1, _), (c2, _)| c1.cmp(c2));
+        let mut collisions = vec![];
+        for (collision, from_b) in events {
+            // setting rather than toggling keeps hits on shared edges of meshes from flipping twice
+            if from_b {
+                in_b = collision.front_face;
+            } else {
+                in_a = collision.front_face;
+            }
+            let now_inside = self.operation.contains(in_a, in_b);
+            if now_inside == inside {
+                continue;
+            }
+            inside = now_inside;
+            if from_b && self.operation == CsgOperation::Difference {
+                collisions.push(flip(collision));
+            } else {
+                collisions.push(collision);
+            }
+        }
+        collisions
+    }
+
+    fn object_count(&self) -> usize {
+        self.a.object_count() + self.b.object_count()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.a.is_closed() && self.b.is_closed()
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        match self.operation {
+            CsgOperation::Union => Some(self.a.bounds()?.union(&self.b.bounds()?)),
+            CsgOperation::Intersection => match (self.a.bounds(), self.b.bounds()) {
+                (Some(a), Some(b)) => {
+                    let min = Vec3::new(
+                        a.min.x.max(b.min.x),
+                        a.min.y.max(b.min.y),
+                        a.min.z.max(b.min.z),
+                    );
+                    let max = Vec3::new(
+                        a.max.x.min(b.max.x),
+                        a.max.y.min(b.max.y),
+                        a.max.z.min(b.max.z),
+                    );
+                    // disjoint shapes leave an empty box at the corner of the overlap
+                    Some(Aabb::new(
+                        min,
+                        Vec3::new(max.x.max(min.x), max.y.max(min.y), max.z.max(min.z)),
+                    ))
+                }
+                (bounds, None) | (None, bounds) => bounds,
+            },
+            CsgOperation::Difference => self.a.bounds(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        img::Color,
+        material::{Lambertian, Material},
+        math::{Ray, Vec3},
+        shapes::{Cuboid, Cylinder, Shape, Sphere, TriangleMesh},
+    };
+
+    use super::Csg;
+
+    fn sphere(x: f64) -> Sphere<Lambertian> {
+        Sphere::new(Vec3::new(x, 0., 0.), 1., Color::WHITE, Lambertian)
+    }
+
+    fn distances<S: Shape>(shape: &S, ray: Ray) -> Vec<f64> {
+        shape
+            .ray_intersections(ray, false)
+            .iter()
+            .map(|collision| collision.distance)
+            .collect()
+    }
+
+    fn assert_distances(actual: Vec<f64>, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (a, b) in actual.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-6, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn test_all_intersections() {
+        let ray = Ray::new(Vec3::new(-5., 0., 0.), Vec3::X);
+        assert_distances(distances(&sphere(0.), ray), &[4., 6.]);
+        // bit i of a vertex index is the sign of coordinate i
+        let corner = |i: usize| {
+            Vec3::new((i & 1) as f64, (i >> 1 & 1) as f64, (i >> 2 & 1) as f64) * 2.
+                - Vec3::splat(1.)
+        };
+        let cube = TriangleMesh::new(
+            (0..8).map(corner).collect(),
+            vec![Color::WHITE],
+            vec![
+                ([0, 2, 3], 0),
+                ([0, 3, 1], 0),
+                ([4, 5, 7], 0),
+                ([4, 7, 6], 0),
+                ([0, 1, 5], 0),
+                ([0, 5, 4], 0),
+                ([2, 6, 7], 0),
+                ([2, 7, 3], 0),
+                ([0, 4, 6], 0),
+                ([0, 6, 2], 0),
+                ([1, 3, 7], 0),
+                ([1, 7, 5], 0),
+            ],
+            Lambertian,
+        );
+        let off_diagonal = Ray::new(Vec3::new(-5., 0.2, 0.1), Vec3::X);
+        let hits = cube.ray_intersections(off_diagonal, false);
+        assert_distances(hits.iter().map(|c| c.distance).collect(), &[4., 6.]);
+        assert!(hits[0].front_face && !hits[1].front_face);
+        assert!(cube.is_closed());
+        let mut open = cube.clone();
+        open.triangles.pop();
+        assert!(!open.is_closed());
+    }
+
+    #[test]
+    fn test_operations() {
+        let ray = Ray::new(Vec3::new(-5., 0., 0.), Vec3::X);
+        let union = Csg::union(sphere(0.), sphere(1.));
+        assert_distances(distances(&union, ray.clone()), &[4., 7.]);
+        let intersection = Csg::intersection(sphere(0.), sphere(1.));
+        assert_distances(distances(&intersection, ray.clone()), &[5., 6.]);
+        let difference = Csg::difference(sphere(0.), sphere(1.));
+        assert_distances(distances(&difference, ray.clone()), &[4., 5.]);
+        // the cut faces out of the remaining solid
+        let collisions = difference.ray_intersections(ray.clone(), false);
+        assert!(!collisions[1].front_face);
+        assert_eq!(collisions[1].normal, Vec3::X);
+        assert_eq!(collisions[1].material.normal(), Vec3::X);
+        // starting inside the union
+        let inside = Ray::new(Vec3::new(0.5, 0., 0.), Vec3::X);
+        assert_distances(distances(&union, inside), &[1.5]);
+        // nested csg, a box drilled through along y and z
+        let drill = |axis: Vec3| {
+            Cylinder::new(axis * -2., axis * 2., 0.5, Color::WHITE, Lambertian).with_caps(true)
+        };
+        let drilled = Csg::difference(
+            Cuboid::new(Vec3::splat(-1.), Vec3::splat(1.), Color::WHITE, Lambertian),
+            Csg::union(drill(Vec3::Y), drill(Vec3::Z)),
+        );
+        assert_distances(distances(&drilled, ray), &[4., 4.5, 5.5, 6.]);
+        let beside = Ray::new(Vec3::new(0.8, 5., 0.), Vec3::NEG_Y);
+        assert_distances(distances(&drilled, beside), &[4., 6.]);
+        let down = Ray::new(Vec3::new(0., 5., 0.), Vec3::NEG_Y);
+        assert_distances(distances(&drilled, down), &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected closed shapes")]
+    fn test_open_operand() {
+        let tube = Cylinder::new(Vec3::NEG_Y, Vec3::Y, 0.5, Color::WHITE, Lambertian);
+        Csg::difference(sphere(0.), tube);
+    }
+}
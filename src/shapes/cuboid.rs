@@ -0,0 +1,164 @@
+use crate::{
+    img::Color,
+    material::{ColorMaterial, Reflector},
+    math::{Aabb, Quaternion, Ray, Vec2, Vec3},
+    texture::Texture,
+    EPSILON,
+};
+
+use super::{Collision, Shape};
+
+/// A solid box, rotated around its center. Each face maps uvs from 0 to 1 and has its own
+/// primitive id, ordered -x, +x, -y, +y, -z, +z
+#[derive(Debug, Clone)]
+pub struct Cuboid<R: Reflector + Clone, T: Texture = Color> {
+    pub center: Vec3,
+    pub half_size: Vec3,
+    pub rotation: Quaternion,
+    pub color: T,
+    pub reflector: R,
+}
+
+impl<R: Reflector + Clone, T: Texture> Cuboid<R, T> {
+    // axis aligned between two opposite corners
+    pub fn new(min: Vec3, max: Vec3, color: T, reflector: R) -> Self {
+        Self {
+            center: (min + max) / 2.,
+            half_size: (max - min) / 2.,
+            rotation: Quaternion::IDENTITY,
+            color,
+            reflector,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Quaternion) -> Self {
+        self.rotation = rotation.normalize();
+        self
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> Shape for Cuboid<R, T> {
+    type Material = ColorMaterial<R>;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        let to_local = self.rotation.conjugate();
+        let start = to_local.rotate(ray.start - self.center);
+        let dir = to_local.rotate(ray.dir);
+        // the ray is inside every slab between near and far
+        let (mut near, mut far) = ((f64::NEG_INFINITY, 0), (f64::INFINITY, 0));
+        for axis in 0..3 {
+            let inverse = dir[axis].recip();
+            let (mut t0, mut t1) = (
+                (-self.half_size[axis] - start[axis]) * inverse,
+                (self.half_size[axis] - start[axis]) * inverse,
+            );
+            if t0 > t1 {
+                (t0, t1) = (t1, t0);
+            }
+            if t0 > near.0 {
+                near = (t0, axis);
+            }
+            if t1 < far.0 {
+                far = (t1, axis);
+            }
+        }
+        if near.0 > far.0 {
+            return None;
+        }
+        let is_valid = |distance: f64| distance > -EPSILON && (include_start || distance > EPSILON);
+        // from the inside the ray leaves through the far face
+        let (distance, axis, sign) = if is_valid(near.0) {
+            (near.0, near.1, -dir[near.1].signum())
+        } else if is_valid(far.0) {
+            (far.0, far.1, dir[far.1].signum())
+        } else {
+            return None;
+        };
+        let mut local_normal = Vec3::ZERO;
+        local_normal[axis] = sign;
+        let normal = self.rotation.rotate(local_normal);
+        let point = start + dir * distance;
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        let uv = Vec2::new(
+            (point[u_axis] / self.half_size[u_axis] + 1.) / 2.,
+            (point[v_axis] / self.half_size[v_axis] + 1.) / 2.,
+        );
+        let color = self.color.sample(uv, ray.point_at(distance));
+        Some(
+            Collision::new(
+                &ray,
+                distance,
+                normal,
+                ColorMaterial::new(normal, color, self.reflector.clone()),
+            )
+            .with_uv(uv)
+            .with_primitive_id(2 * axis + (sign > 0.) as usize),
+        )
+    }
+
+    fn is_closed(&self) -> bool {
+        true
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let local = Aabb::new(-self.half_size, self.half_size);
+        Aabb::from_points(
+            local
+                .corners()
+                .map(|corner| self.center + self.rotation.rotate(corner)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_4;
+
+    use crate::{
+        img::Color,
+        material::Lambertian,
+        math::{assert_close, Quaternion, Ray, Vec3},
+        shapes::Shape,
+    };
+
+    use super::Cuboid;
+
+    #[test]
+    fn test_axis_aligned() {
+        let cuboid = Cuboid::new(
+            Vec3::new(0., 0., 0.),
+            Vec3::new(2., 1., 4.),
+            Color::WHITE,
+            Lambertian,
+        );
+        let ray = Ray::new(Vec3::new(1., 5., 1.), Vec3::NEG_Y);
+        let collision = cuboid.ray_intersection(ray, false).unwrap();
+        assert!((collision.distance - 4.).abs() < 1e-9);
+        assert_close(collision.normal, Vec3::Y);
+        assert_eq!(collision.primitive_id, 3);
+        assert!((collision.uv.x - 0.25).abs() < 1e-9 && (collision.uv.y - 0.5).abs() < 1e-9);
+        // leaving through the far side from the inside
+        let inside = Ray::new(Vec3::new(1., 0.5, 1.), Vec3::X);
+        let collision = cuboid.ray_intersection(inside, false).unwrap();
+        assert!((collision.distance - 1.).abs() < 1e-9);
+        assert!(!collision.front_face);
+        let miss = Ray::new(Vec3::new(3., 5., 1.), Vec3::NEG_Y);
+        assert!(cuboid.ray_intersection(miss, false).is_none());
+        let behind = Ray::new(Vec3::new(1., -1., 1.), Vec3::NEG_Y);
+        assert!(cuboid.ray_intersection(behind, false).is_none());
+    }
+
+    #[test]
+    fn test_rotated() {
+        let cuboid = Cuboid::new(Vec3::splat(-1.), Vec3::splat(1.), Color::WHITE, Lambertian)
+            .with_rotation(Quaternion::from_axis_angle(Vec3::Y, FRAC_PI_4));
+        let ray = Ray::new(Vec3::new(-5., 0., 0.), Vec3::X);
+        let collision = cuboid.ray_intersection(ray, false).unwrap();
+        assert!((collision.distance - (5. - 2f64.sqrt())).abs() < 1e-9);
+        let bounds = cuboid.bounds().unwrap();
+        assert_close(bounds.max, Vec3::new(2f64.sqrt(), 1., 2f64.sqrt()));
+        let edge = Ray::new(Vec3::new(-5., 0., 0.1), Vec3::X);
+        let normal = cuboid.ray_intersection(edge, false).unwrap().normal;
+        assert_close(normal, Vec3::new(-1., 0., 1.).normalize());
+    }
+}
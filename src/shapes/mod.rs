@@ -0,0 +1,1246 @@
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::HashSet,
+    f64::consts::PI,
+    ops::{Add, Deref, DerefMut, Mul, Range},
+    sync::Arc,
+};
+
+use crate::{
+    img::Color,
+    material::{ColorMaterial, Material, Reflector},
+    math::{Aabb, AnimatedTransform, Mat3x3, Ray, Transform, Vec2, Vec3},
+    texture::{NormalMap, Texture},
+    EPSILON,
+};
+
+mod csg;
+mod cuboid;
+mod plane;
+mod polygon;
+mod revolution;
+
+pub use csg::{Csg, CsgOperation};
+pub use cuboid::Cuboid;
+pub use plane::{Plane, Quad};
+pub use polygon::Polygon;
+pub use revolution::{Cone, Cylinder, Disk, Torus};
+
+#[derive(Debug, Clone)]
+pub struct RayCollision<M: Material> {
+    pub ray: Ray,
+    pub collision: Collision<M>,
+}
+
+impl<M: Material> RayCollision<M> {
+    pub fn new(ray: Ray, collision: Collision<M>) -> Self {
+        Self { ray, collision }
+    }
+
+    pub fn collision_point(&self) -> Vec3 {
+        self.collision.point
+    }
+
+    pub fn reflection(&self) -> Ray {
+        self.material
+            .update_ray(self.ray.translate(self.collision.distance))
+    }
+}
+
+impl<M: Material> Deref for RayCollision<M> {
+    type Target = Collision<M>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.collision
+    }
+}
+
+impl<M: Material> DerefMut for RayCollision<M> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.collision
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Collision<M: Material> {
+    pub distance: f64,
+    pub point: Vec3,
+    // the unperturbed surface normal, the shading normal is the material's
+    pub normal: Vec3,
+    pub front_face: bool,
+    pub uv: Vec2,
+    pub barycentric: Option<Vec3>,
+    pub primitive_id: usize,
+    pub object_id: usize,
+    pub material: M,
+}
+
+impl<M: Material> Collision<M> {
+    pub fn new(ray: &Ray, distance: f64, normal: Vec3, material: M) -> Self {
+        Self {
+            distance,
+            point: ray.point_at(distance),
+            normal,
+            front_face: ray.dir.dot(normal) < 0.,
+            uv: Vec2::ZERO,
+            barycentric: None,
+            primitive_id: 0,
+            object_id: 0,
+            material,
+        }
+    }
+
+    pub fn with_uv(mut self, uv: Vec2) -> Self {
+        self.uv = uv;
+        self
+    }
+
+    pub fn with_barycentric(mut self, barycentric: Vec3) -> Self {
+        self.barycentric = Some(barycentric);
+        self
+    }
+
+    pub fn with_primitive_id(mut self, primitive_id: usize) -> Self {
+        self.primitive_id = primitive_id;
+        self
+    }
+
+    pub fn with_object_id(mut self, object_id: usize) -> Self {
+        self.object_id = object_id;
+        self
+    }
+
+    pub fn shading_normal(&self) -> Vec3 {
+        self.material.normal()
+    }
+
+    pub fn cmp<M2: Material>(&self, other: &Collision<M2>) -> Ordering {
+        self.distance.total_cmp(&other.distance)
+    }
+}
+
+impl<M: Material + Copy> Copy for Collision<M> {}
+
+pub trait Shape {
+    type Material: Material;
+    // the nearest collision along the ray. When ray_start is on some surface, only if
+    // include_start and the ray is facing into the surface, it should return a collision there,
+    // otherwise the ray goes on to the next surface, eg. the far side of a shape it starts inside
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>>;
+
+    // None for unbounded shapes
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+
+    // collisions use object ids from 0 up to this, so nested lists can number their leaves
+    fn object_count(&self) -> usize {
+        1
+    }
+
+    // whether the surface encloses a volume with its normals facing out,
+    // so rays alternate between entering and leaving it
+    fn is_closed(&self) -> bool {
+        false
+    }
+
+    // every crossing of a surface along the ray, sorted by distance,
+    // by default found by restarting the ray past each collision,
+    // which merges surfaces less than EPSILON apart into the first of them
+    fn ray_intersections(&self, ray: Ray, include_start: bool) -> Vec<Collision<Self::Material>> {
+        let mut collisions = vec![];
+        let (mut current, mut include_start, mut travelled) = (ray, include_start, 0.);
+        while let Some(mut collision) = self.ray_intersection(current.clone(), include_start) {
+            current = current.translate(collision.distance);
+            travelled += collision.distance;
+            collision.distance = travelled;
+            collisions.push(collision);
+            include_start = false;
+        }
+        collisions
+    }
+
+    fn intersect_inclusive(&self, ray: Ray) -> Option<RayCollision<Self::Material>> {
+        self.ray_intersection(ray.clone(), true)
+            .map(|collision| RayCollision::new(ray, collision))
+    }
+
+    fn intersect_exclusive(&self, ray: Ray) -> Option<RayCollision<Self::Material>> {
+        self.ray_intersection(ray.clone(), false)
+            .map(|collision| RayCollision::new(ray, collision))
+    }
+}
+
+impl<T> Shape for T
+where
+    T: Deref,
+    T::Target: Shape,
+{
+    type Material = <T::Target as Shape>::Material;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        (**self).ray_intersection(ray, include_start)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        (**self).bounds()
+    }
+
+    fn object_count(&self) -> usize {
+        (**self).object_count()
+    }
+
+    fn is_closed(&self) -> bool {
+        (**self).is_closed()
+    }
+
+    fn ray_intersections(&self, ray: Ray, include_start: bool) -> Vec<Collision<Self::Material>> {
+        (**self).ray_intersections(ray, include_start)
+    }
+}
+
+impl<T> Shape for [T]
+where
+    T: Shape,
+{
+    type Material = T::Material;
+
+    // the objects of each shape are numbered after those of the shapes before it
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        self.iter()
+            .zip(first_object_ids(self))
+            .filter_map(|(shape, first_id)| {
+                shape
+                    .ray_intersection(ray.clone(), include_start)
+                    .map(|collision| {
+                        let object_id = first_id + collision.object_id;
+                        collision.with_object_id(object_id)
+                    })
+            })
+            .min_by(|c1, c2| c1.cmp(c2))
+    }
+
+    // unbounded if any of the shapes is
+    fn bounds(&self) -> Option<Aabb> {
+        self.iter()
+            .map(Shape::bounds)
+            .reduce(|a, b| Some(a?.union(&b?)))
+            .flatten()
+    }
+
+    fn object_count(&self) -> usize {
+        self.iter().map(Shape::object_count).sum()
+    }
+
+    // only while the shapes don't overlap
+    fn is_closed(&self) -> bool {
+        self.iter().all(Shape::is_closed)
+    }
+
+    fn ray_intersections(&self, ray: Ray, include_start: bool) -> Vec<Collision<Self::Material>> {
+        let mut collisions: Vec<_> = self
+            .iter()
+            .zip(first_object_ids(self))
+            .flat_map(|(shape, first_id)| {
+                shape
+                    .ray_intersections(ray.clone(), include_start)
+                    .into_iter()
+                    .map(move |collision| {
+                        let object_id = first_id + collision.object_id;
+                        collision.with_object_id(object_id)
+                    })
+            })
+            .collect();
+        collisions.sort_by(|c1, c2| c1.cmp(c2));
+        collisions
+    }
+}
+
+fn first_object_ids<T: Shape>(shapes: &[T]) -> impl Iterator<Item = usize> + '_ {
+    shapes.iter().scan(0, |next_id, shape| {
+        let first_id = *next_id;
+        *next_id += shape.object_count();
+        Some(first_id)
+    })
+}
+
+// shares one shape between many placements
+#[derive(Debug)]
+pub struct Instance<S: Shape + ?Sized> {
+    pub shape: Arc<S>,
+    pub transform: Transform,
+    // replaces the transform at the ray's time when set
+    pub motion: Option<AnimatedTransform>,
+}
+
+impl<S: Shape + ?Sized> Instance<S> {
+    pub fn new(shape: Arc<S>, transform: Transform) -> Self {
+        Self {
+            shape,
+            transform,
+            motion: None,
+        }
+    }
+
+    pub fn animated(shape: Arc<S>, motion: AnimatedTransform) -> Self {
+        Self {
+            shape,
+            transform: motion.at(0.),
+            motion: Some(motion),
+        }
+    }
+
+    pub fn translated(shape: Arc<S>, translation: Vec3) -> Self {
+        Self::new(shape, Transform::translation(translation))
+    }
+}
+
+impl<S: Shape + ?Sized> Clone for Instance<S> {
+    fn clone(&self) -> Self {
+        Self {
+            shape: Arc::clone(&self.shape),
+            transform: self.transform.clone(),
+            motion: self.motion.clone(),
+        }
+    }
+}
+
+impl<S: Shape + ?Sized> Instance<S> {
+    fn transform_at(&self, time: f64) -> Cow<'_, Transform> {
+        match &self.motion {
+            Some(motion) => Cow::Owned(motion.at(time)),
+            None => Cow::Borrowed(&self.transform),
+        }
+    }
+
+    // the ray in object space, with the factor that scales its distances back to world space
+    fn object_ray(transform: &Transform, ray: &Ray) -> (Ray, f64) {
+        let to_object = transform.inverse_matrix();
+        let dir = to_object.transform_vector(ray.dir);
+        // object space distances are scaled by the length of the transformed direction
+        let scale = dir.magnitude();
+        let object_ray =
+            Ray::new(to_object.transform_point(ray.start), dir / scale).with_time(ray.time);
+        (object_ray, scale)
+    }
+
+    fn to_world(
+        transform: &Transform,
+        ray: &Ray,
+        scale: f64,
+        mut collision: Collision<S::Material>,
+    ) -> Collision<S::Material> {
+        collision.distance /= scale;
+        collision.point = ray.point_at(collision.distance);
+        collision.normal = transform.apply_normal(collision.normal).normalize();
+        collision.front_face = ray.dir.dot(collision.normal) < 0.;
+        let shading_normal = transform
+            .apply_normal(collision.shading_normal())
+            .normalize();
+        collision.material.set_normal(shading_normal);
+        collision
+    }
+}
+
+fn is_valid_world_distance(distance: f64, include_start: bool) -> bool {
+    distance > -EPSILON && (include_start || distance > EPSILON)
+}
+
+impl<S: Shape + ?Sized> Shape for Instance<S> {
+    type Material = S::Material;
+
+    // the shape only knows its object space tolerance, so hits are accepted by their world
+    // distance and the ray is restarted past any the shape accepted too close to the start
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        let transform = self.transform_at(ray.time);
+        let (mut object_ray, scale) = Self::object_ray(&transform, &ray);
+        let (mut travelled, mut object_include_start) = (0., true);
+        loop {
+            let mut collision = self
+                .shape
+                .ray_intersection(object_ray.clone(), object_include_start)?;
+            object_ray = object_ray.translate(collision.distance);
+            collision.distance += travelled;
+            if is_valid_world_distance(collision.distance / scale, include_start) {
+                return Some(Self::to_world(&transform, &ray, scale, collision));
+            }
+            travelled = collision.distance;
+            object_include_start = false;
+        }
+    }
+
+    fn ray_intersections(&self, ray: Ray, include_start: bool) -> Vec<Collision<Self::Material>> {
+        let transform = self.transform_at(ray.time);
+        let (object_ray, scale) = Self::object_ray(&transform, &ray);
+        self.shape
+            .ray_intersections(object_ray, true)
+            .into_iter()
+            .filter(|collision| is_valid_world_distance(collision.distance / scale, include_start))
+            .map(|collision| Self::to_world(&transform, &ray, scale, collision))
+            .collect()
+    }
+
+    fn object_count(&self) -> usize {
+        self.shape.object_count()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.shape.is_closed()
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let bounds = self.shape.bounds()?;
+        match &self.motion {
+            Some(motion) => Some(motion.bounds(&bounds)),
+            None => Some(bounds.transform(&self.transform)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TriangleMesh<R: Reflector + Clone, T: Texture = Color> {
+    pub vertices: Vec<Vec3>,
+    pub triangles: Vec<[u16; 3]>,
+    pub tri_colors: Vec<u16>,
+    pub triangle_projections: Vec<Mat3x3>,
+    pub normals: Vec<Vec3>,
+    // per triangle corner, so creases can split a vertex
+    pub vertex_normals: Option<Vec<[Vec3; 3]>>,
+    pub uvs: Option<Vec<Vec2>>,
+    // per vertex tangent and bitangent
+    pub tangents: Option<Vec<[Vec3; 2]>>,
+    pub normal_map: Option<NormalMap>,
+    pub colors: Vec<T>,
+    pub reflector: R,
+}
+
+pub type VertexIndex = u16;
+pub type ColorIndex = u16;
+
+impl<R: Reflector + Clone, T: Texture> TriangleMesh<R, T> {
+    pub fn new(
+        vertices: Vec<Vec3>,
+        colors: Vec<T>,
+        triangles: Vec<([VertexIndex; 3], ColorIndex)>,
+        reflector: R,
+    ) -> Self {
+        let tri_colors = triangles.iter().map(|(_, c)| *c).collect();
+        let normals: Vec<_> = triangles
+            .iter()
+            .copied()
+            .map(|([a, b, c], _)| {
+                (vertices[b as usize] - vertices[a as usize])
+                    .cross(vertices[c as usize] - vertices[b as usize])
+                    .normalize()
+            })
+            .collect();
+        let triangle_projections = triangles
+            .iter()
+            .copied()
+            .zip(normals.iter().cloned())
+            .map(|(([a, b, c], _), normal)| {
+                let v100 = vertices[b as usize] - vertices[a as usize];
+                let v010 = vertices[c as usize] - vertices[a as usize];
+                // z is the height above the plane of the triangle
+                let fwd_change_of_basis = Mat3x3::from_col_vectors(v100, v010, normal);
+                fwd_change_of_basis.inverse().unwrap()
+            })
+            .collect();
+        let triangles = triangles
+            .into_iter()
+            .map(|([a, b, c], _)| [a, b, c])
+            .collect();
+        Self {
+            vertices,
+            triangles,
+            tri_colors,
+            triangle_projections,
+            normals,
+            vertex_normals: None,
+            uvs: None,
+            tangents: None,
+            normal_map: None,
+            colors,
+            reflector,
+        }
+    }
+
+    // one uv per vertex, without them the barycentric coordinates are used
+    pub fn with_uvs(mut self, uvs: Vec<Vec2>) -> Self {
+        assert_eq!(uvs.len(), self.vertices.len(), "Expected one uv per vertex");
+        self.uvs = Some(uvs);
+        self
+    }
+
+    pub fn with_vertex_normals(mut self, normals: Vec<Vec3>) -> Self {
+        assert_eq!(
+            normals.len(),
+            self.vertices.len(),
+            "Expected one normal per vertex"
+        );
+        self.vertex_normals = Some(
+            self.triangles
+                .iter()
+                .map(|triangle| triangle.map(|i| normals[i as usize].normalize()))
+                .collect(),
+        );
+        self
+    }
+
+    // averages the normals of the faces around each vertex weighted by their angle at it,
+    // faces more than crease_angle degrees apart are not smoothed together
+    pub fn with_smooth_normals(mut self, crease_angle: f64) -> Self {
+        let crease_cos = crease_angle.to_radians().cos();
+        let mut vertex_faces = vec![Vec::new(); self.vertices.len()];
+        for (i, triangle) in self.triangles.iter().enumerate() {
+            for (corner, &vertex) in triangle.iter().enumerate() {
+                let [a, b, c] =
+                    [0, 1, 2].map(|j| self.vertices[triangle[(corner + j) % 3] as usize]);
+                vertex_faces[vertex as usize].push((i, (b - a).angle_to(c - a)));
+            }
+        }
+        self.vertex_normals = Some(
+            self.triangles
+                .iter()
+                .enumerate()
+                .map(|(i, triangle)| {
+                    triangle.map(|vertex| {
+                        vertex_faces[vertex as usize]
+                            .iter()
+                            .filter(|&&(j, _)| self.normals[i].dot(self.normals[j]) >= crease_cos)
+                            .fold(Vec3::ZERO, |sum, &(j, angle)| sum + self.normals[j] * angle)
+                            .normalize_or_zero()
+                    })
+                })
+                .collect(),
+        );
+        self
+    }
+
+    pub fn with_tangents(mut self, tangents: Vec<[Vec3; 2]>) -> Self {
+        assert_eq!(
+            tangents.len(),
+            self.vertices.len(),
+            "Expected one tangent per vertex"
+        );
+        self.tangents = Some(tangents);
+        self
+    }
+
+    // generates tangents from the uvs unless they were supplied, without uvs the
+    // edges of each triangle are used as it is mapped by its barycentric coordinates
+    pub fn with_normal_map(mut self, normal_map: NormalMap) -> Self {
+        if self.tangents.is_none() && self.uvs.is_some() {
+            self.tangents = Some(self.generate_tangents());
+        }
+        self.normal_map = Some(normal_map);
+        self
+    }
+
+    fn generate_tangents(&self) -> Vec<[Vec3; 2]> {
+        let uvs = self
+            .uvs
+            .as_ref()
+            .expect("Expected uvs to generate tangents from");
+        let mut tangents = vec![[Vec3::ZERO; 2]; self.vertices.len()];
+        for &[a, b, c] in &self.triangles {
+            let [a, b, c] = [a as usize, b as usize, c as usize];
+            let (e1, e2) = (
+                self.vertices[b] - self.vertices[a],
+                self.vertices[c] - self.vertices[a],
+            );
+            let (d1, d2) = (uvs[b] - uvs[a], uvs[c] - uvs[a]);
+            let r = d1.cross(d2).recip();
+            if !r.is_finite() {
+                continue;
+            }
+            let tangent = (e1 * d2.y - e2 * d1.y) * r;
+            let bitangent = (e2 * d1.x - e1 * d2.x) * r;
+            for i in [a, b, c] {
+                tangents[i][0] += tangent;
+                tangents[i][1] += bitangent;
+            }
+        }
+        tangents
+    }
+
+    fn shading_normal(&self, triangle: usize, barycentric: Vec3, uv: Vec2, point: Vec3) -> Vec3 {
+        let normal = match &self.vertex_normals {
+            Some(normals) => {
+                let normal = interpolate(normals[triangle], barycentric).normalize_or_zero();
+                if normal == Vec3::ZERO {
+                    self.normals[triangle]
+                } else {
+                    normal
+                }
+            }
+            None => self.normals[triangle],
+        };
+        let Some(normal_map) = &self.normal_map else {
+            return normal;
+        };
+        let (tangent, bitangent) = match &self.tangents {
+            Some(tangents) => {
+                let corners = self.triangles[triangle].map(|i| tangents[i as usize]);
+                (
+                    interpolate(corners.map(|[t, _]| t), barycentric),
+                    interpolate(corners.map(|[_, b]| b), barycentric),
+                )
+            }
+            None => {
+                let [a, b, c] = self.triangles[triangle].map(|i| self.vertices[i as usize]);
+                (b - a, c - a)
+            }
+        };
+        let (tangent, bitangent) = orthonormal_tangents(normal, tangent, bitangent);
+        normal_map.perturb(normal, tangent, bitangent, uv, point)
+    }
+
+    // triangle index, distance and barycentric coordinates of every triangle the ray hits,
+    // only from the front unless two_sided
+    fn triangle_hits<'a>(
+        &'a self,
+        ray: &'a Ray,
+        include_start: bool,
+        two_sided: bool,
+    ) -> impl Iterator<Item = (usize, f64, Vec3)> + 'a {
+        self.triangles
+            .iter()
+            .copied()
+            .zip(self.triangle_projections.iter())
+            .enumerate()
+            .filter_map(move |(i, ([a, _b, _c], projection))| {
+                let facing = ray.dir.dot(self.normals[i]);
+                if (two_sided && facing.abs() <= EPSILON) || (!two_sided && facing > -EPSILON) {
+                    return None;
+                }
+                let start_in_triangle_space = projection * (ray.start - self.vertices[a as usize]);
+                let ray_in_triangle_space = projection * ray.dir;
+                let ray_scale = -start_in_triangle_space.z / ray_in_triangle_space.z;
+                let mut uvw = ray_in_triangle_space * ray_scale + start_in_triangle_space;
+                uvw.z = 0.;
+                if !ray_scale.is_finite()
+                    || ray_scale <= -EPSILON
+                    || (!include_start && ray_scale <= EPSILON)
+                    || uvw.x + uvw.y > 1. + EPSILON
+                    || uvw.x < -EPSILON
+                    || uvw.y < -EPSILON
+                    || !uvw.x.is_finite()
+                    || !uvw.y.is_finite()
+                {
+                    return None;
+                }
+                Some((i, ray_scale, uvw))
+            })
+    }
+
+    fn collision(
+        &self,
+        ray: &Ray,
+        triangle: usize,
+        distance: f64,
+        barycentric: Vec3,
+    ) -> Collision<ColorMaterial<R>> {
+        let (uv, point) = (self.uv_at(triangle, barycentric), ray.point_at(distance));
+        let color = self.colors[self.tri_colors[triangle] as usize].sample(uv, point);
+        let normal = self.shading_normal(triangle, barycentric, uv, point);
+        Collision::new(
+            ray,
+            distance,
+            self.normals[triangle],
+            ColorMaterial::new(normal, color, self.reflector.clone()),
+        )
+        .with_uv(uv)
+        .with_barycentric(barycentric)
+        .with_primitive_id(triangle)
+    }
+
+    fn uv_at(&self, triangle: usize, barycentric: Vec3) -> Vec2 {
+        let Some(uvs) = &self.uvs else {
+            return Vec2::new(barycentric.x, barycentric.y);
+        };
+        interpolate(
+            self.triangles[triangle].map(|i| uvs[i as usize]),
+            barycentric,
+        )
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> Shape for TriangleMesh<R, T> {
+    type Material = ColorMaterial<R>;
+
+    fn ray_intersection(
+        &self,
+        ray: Ray,
+        include_start: bool,
+    ) -> Option<Collision<ColorMaterial<R>>> {
+        self.triangle_hits(&ray, include_start, false)
+            .min_by(|(_, d1, _), (_, d2, _)| d1.total_cmp(d2))
+            .map(|(i, distance, uvw)| self.collision(&ray, i, distance, uvw))
+    }
+
+    // closed meshes are left through the back of their triangles
+    fn ray_intersections(&self, ray: Ray, include_start: bool) -> Vec<Collision<Self::Material>> {
+        let mut collisions: Vec<_> = self
+            .triangle_hits(&ray, include_start, true)
+            .map(|(i, distance, uvw)| self.collision(&ray, i, distance, uvw))
+            .collect();
+        collisions.sort_by(|c1, c2| c1.cmp(c2));
+        collisions
+    }
+
+    // every edge is shared with exactly one triangle running it the other way
+    fn is_closed(&self) -> bool {
+        let mut edges = HashSet::new();
+        let unique = self
+            .triangles
+            .iter()
+            .flat_map(|&[a, b, c]| [(a, b), (b, c), (c, a)])
+            .all(|edge| edges.insert(edge));
+        unique && edges.iter().all(|&(a, b)| edges.contains(&(b, a)))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().copied())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sphere<R: Reflector + Clone, T: Texture = Color> {
+    pub center: Vec3,
+    pub radius: f64,
+    pub color: T,
+    pub normal_map: Option<NormalMap>,
+    // the center moves in a straight line to where it ends up between the two ray times
+    pub motion: Option<(Vec3, Range<f64>)>,
+    pub reflector: R,
+}
+
+impl<R: Reflector + Clone, T: Texture> Sphere<R, T> {
+    pub const fn new(center: Vec3, radius: f64, color: T, reflector: R) -> Self {
+        Self {
+            center,
+            radius,
+            color,
+            normal_map: None,
+            motion: None,
+            reflector,
+        }
+    }
+
+    // the sphere stays at center before start_time and at end_center after end_time
+    pub fn with_motion(mut self, end_center: Vec3, start_time: f64, end_time: f64) -> Self {
+        assert!(
+            start_time < end_time,
+            "Expected the motion to start before it ends"
+        );
+        self.motion = Some((end_center, start_time..end_time));
+        self
+    }
+
+    pub fn center_at(&self, time: f64) -> Vec3 {
+        let Some((end_center, times)) = &self.motion else {
+            return self.center;
+        };
+        let t = ((time - times.start) / (times.end - times.start)).clamp(0., 1.);
+        self.center * (1. - t) + *end_center * t
+    }
+
+    pub fn with_normal_map(mut self, normal_map: NormalMap) -> Self {
+        self.normal_map = Some(normal_map);
+        self
+    }
+
+    fn shading_normal(&self, normal: Vec3, uv: Vec2, point: Vec3) -> Vec3 {
+        let Some(normal_map) = &self.normal_map else {
+            return normal;
+        };
+        // the derivatives of the position with respect to u and v
+        let mut tangent = Vec3::new(normal.z, 0., -normal.x).normalize_or_zero();
+        if tangent == Vec3::ZERO {
+            tangent = Vec3::X;
+        }
+        let bitangent = normal.cross(tangent);
+        normal_map.perturb(normal, tangent, bitangent, uv, point)
+    }
+
+    fn intersect_equation(&self, ray: Ray) -> (Vec3, f64, f64) {
+        let relative_center = self.center_at(ray.time) - ray.start;
+        let cx = relative_center.dot(ray.dir);
+        let cc = relative_center.dot(relative_center);
+        let xx = ray.dir.dot(ray.dir);
+        let rr = self.radius * self.radius;
+        let root = cx * cx - xx * (cc - rr);
+        (relative_center, cx, root)
+    }
+
+    // longitude and latitude, with the poles on the y axis
+    pub fn uv(normal: Vec3) -> Vec2 {
+        Vec2::new(
+            0.5 + (-normal.z).atan2(normal.x) / (2. * PI),
+            0.5 + normal.y.clamp(-1., 1.).asin() / PI,
+        )
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> Shape for Sphere<R, T> {
+    type Material = ColorMaterial<R>;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        let (relative_center, cx, root) = self.intersect_equation(ray.clone());
+        if root.is_sign_negative() {
+            return None;
+        }
+        let mut l = cx - root.sqrt();
+        if !include_start && l < EPSILON {
+            // the ray starts inside, eg. after refracting into the sphere
+            l = cx + root.sqrt();
+            if l < EPSILON {
+                return None;
+            }
+        }
+        let normal = (ray.dir * l - relative_center) / self.radius;
+        let (uv, point) = (Self::uv(normal), ray.point_at(l));
+        let color = self.color.sample(uv, point);
+        Some(
+            Collision::new(
+                &ray,
+                l,
+                normal,
+                ColorMaterial::new(
+                    self.shading_normal(normal, uv, point),
+                    color,
+                    self.reflector.clone(),
+                ),
+            )
+            .with_uv(uv),
+        )
+    }
+
+    fn is_closed(&self) -> bool {
+        true
+    }
+
+    // covers the whole motion
+    fn bounds(&self) -> Option<Aabb> {
+        let radius = Vec3::splat(self.radius);
+        let end = self.motion.as_ref().map_or(self.center, |(end, _)| *end);
+        Some(
+            Aabb::new(self.center - radius, self.center + radius)
+                .union(&Aabb::new(end - radius, end + radius)),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvertedSphere<R: Reflector + Clone, T: Texture = Color>(Sphere<R, T>);
+
+impl<R: Reflector + Clone, T: Texture> InvertedSphere<R, T> {
+    pub fn new(center: Vec3, radius: f64, color: T, reflector: R) -> Self {
+        Self(Sphere::new(center, radius, color, reflector))
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> From<Sphere<R, T>> for InvertedSphere<R, T> {
+    fn from(value: Sphere<R, T>) -> Self {
+        Self(value)
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> From<InvertedSphere<R, T>> for Sphere<R, T> {
+    fn from(value: InvertedSphere<R, T>) -> Self {
+        value.0
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> Shape for InvertedSphere<R, T> {
+    type Material = ColorMaterial<R>;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        let (relative_center, cx, root) = self.0.intersect_equation(ray.clone());
+        if root.is_sign_negative() {
+            return None;
+        }
+        // rays starting outside the sphere cross the near wall from its back first,
+        // which never counts as facing into it at the start
+        let mut l = cx - root.sqrt();
+        if l < EPSILON {
+            l = cx + root.sqrt();
+            if !include_start && l < EPSILON {
+                return None;
+            }
+        }
+        let outward = (ray.dir * l - relative_center) / self.0.radius;
+        let (uv, point) = (Sphere::<R, T>::uv(outward), ray.point_at(l));
+        let color = self.0.color.sample(uv, point);
+        Some(
+            Collision::new(
+                &ray,
+                l,
+                -outward,
+                ColorMaterial::new(
+                    -self.0.shading_normal(outward, uv, point),
+                    color,
+                    self.0.reflector.clone(),
+                ),
+            )
+            .with_uv(uv),
+        )
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.0.bounds()
+    }
+}
+
+// barycentric.x and barycentric.y are the weights of b and c
+fn interpolate<V: Mul<f64, Output = V> + Add<Output = V>>(
+    [a, b, c]: [V; 3],
+    barycentric: Vec3,
+) -> V {
+    a * (1. - barycentric.x - barycentric.y) + b * barycentric.x + c * barycentric.y
+}
+
+// gram-schmidt orthogonalizes the tangent and keeps the handedness of the bitangent
+fn orthonormal_tangents(normal: Vec3, tangent: Vec3, bitangent: Vec3) -> (Vec3, Vec3) {
+    let tangent = tangent.reject_wrt(normal).normalize_or_zero();
+    let bitangent_dir = normal.cross(tangent);
+    if bitangent.dot(bitangent_dir) < 0. {
+        (tangent, -bitangent_dir)
+    } else {
+        (tangent, bitangent_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f64::consts::FRAC_PI_2, sync::Arc};
+
+    use crate::{
+        img::{Color, ColorSpace, Image},
+        material::{Lambertian, Material},
+        math::{assert_close, Aabb, AnimatedTransform, Keyframe, Quaternion, Ray, Transform, Vec3},
+        scene::Display,
+        texture::{ImageTexture, NormalMap},
+    };
+
+    use super::{Cuboid, Instance, InvertedSphere, Shape, Sphere, TriangleMesh};
+
+    fn fold() -> TriangleMesh<Lambertian> {
+        TriangleMesh::new(
+            // the triangle planes can't pass through the origin
+            [Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::Z]
+                .map(|v| v + Vec3::splat(1.))
+                .to_vec(),
+            vec![Color::WHITE],
+            vec![([0, 1, 2], 0), ([0, 3, 1], 0)],
+            Lambertian,
+        )
+    }
+
+    #[test]
+    fn test_crease_angle() {
+        let mesh = fold().with_smooth_normals(30.);
+        for (corners, normal) in mesh.vertex_normals.unwrap().iter().zip(mesh.normals) {
+            assert_eq!(*corners, [normal; 3]);
+        }
+        let mesh = fold().with_smooth_normals(100.);
+        let normals = mesh.vertex_normals.unwrap();
+        let expected = (mesh.normals[0] + mesh.normals[1]).normalize();
+        assert!((normals[0][0] - expected).l1_norm() < 1e-9);
+        assert!((normals[1][0] - expected).l1_norm() < 1e-9);
+        assert!((normals[0][2] - mesh.normals[0]).l1_norm() < 1e-9);
+    }
+
+    #[test]
+    fn test_vertex_normals() {
+        let corner_normals = [Vec3::Z, Vec3::new(1., 0., 1.), Vec3::new(0., 1., 1.)];
+        let mesh = TriangleMesh::new(
+            vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            vec![Color::WHITE],
+            vec![([0, 1, 2], 0)],
+            Lambertian,
+        )
+        .with_vertex_normals(corner_normals.to_vec());
+        let ray = Ray::new(Vec3::new(0.25, 0.5, 2.), Vec3::NEG_Z);
+        let collision = mesh.ray_intersection(ray, false).unwrap();
+        assert_eq!(collision.barycentric, Some(Vec3::new(0.25, 0.5, 0.)));
+        let [a, b, c] = corner_normals.map(Vec3::normalize);
+        let expected = (a * 0.25 + b * 0.25 + c * 0.5).normalize();
+        assert_close(collision.material.normal(), expected);
+        assert_close(collision.shading_normal(), expected);
+        // the geometric normal stays flat
+        assert_eq!(collision.normal, Vec3::Z);
+    }
+
+    #[test]
+    fn test_normal_map_without_uvs() {
+        let mut img = Image::zeros(Display::new(1, 1));
+        *img.at_mut(0, 0) = Color::from_rgb(0.5, 0.5, 1.);
+        let flat = NormalMap::TangentSpace(ImageTexture::new(img.non_color(), ColorSpace::Srgb));
+        let mesh = fold().with_normal_map(flat);
+        let ray = Ray::new(mesh.vertices[0] + Vec3::new(0.25, 0.25, 2.), Vec3::NEG_Z);
+        let collision = mesh.ray_intersection(ray, false).unwrap();
+        assert!((collision.material.normal() - collision.normal).l1_norm() < 1e-9);
+    }
+
+    #[test]
+    fn test_sphere_from_inside() {
+        let sphere = Sphere::new(Vec3::ZERO, 1., Color::WHITE, Lambertian);
+        let distance = |start: Vec3, include_start| {
+            let ray = Ray::new(start, Vec3::NEG_Z);
+            sphere
+                .ray_intersection(ray, include_start)
+                .map(|collision| collision.distance)
+        };
+        assert_eq!(distance(Vec3::new(0., 0., 5.), false), Some(4.));
+        // rays starting inside go on to the far wall, which they leave through its back
+        assert_eq!(distance(Vec3::ZERO, false), Some(1.));
+        let collision = sphere
+            .ray_intersection(Ray::new(Vec3::ZERO, Vec3::NEG_Z), false)
+            .unwrap();
+        assert!(!collision.front_face);
+        assert_eq!(collision.normal, Vec3::NEG_Z);
+        // starting on the near wall, as after refracting into the sphere
+        assert_eq!(distance(Vec3::Z, true), Some(0.));
+        assert_eq!(distance(Vec3::Z, false), Some(2.));
+        assert_eq!(distance(Vec3::NEG_Z, false), None);
+        // the inside of an inverted sphere faces the center
+        let inverted = InvertedSphere::new(Vec3::ZERO, 1., Color::WHITE, Lambertian);
+        let hit = |start: Vec3| {
+            let ray = Ray::new(start, Vec3::NEG_Z);
+            inverted.ray_intersection(ray, false).unwrap()
+        };
+        assert_eq!(hit(Vec3::ZERO).distance, 1.);
+        assert!(hit(Vec3::ZERO).front_face);
+        assert_eq!(hit(Vec3::new(0., 0., 5.)).distance, 4.);
+        assert!(!hit(Vec3::new(0., 0., 5.)).front_face);
+    }
+
+    #[test]
+    fn test_mesh_intersection() {
+        let mesh = fold();
+        let corner = mesh.vertices[0];
+        let ray = Ray::new(corner + Vec3::new(0.25, 0.25, 2.), Vec3::NEG_Z);
+        let collision = mesh.ray_intersection(ray, false).unwrap();
+        assert!((collision.distance - 2.).abs() < 1e-9);
+        assert_eq!(collision.barycentric, Some(Vec3::new(0.25, 0.25, 0.)));
+        // triangles are only hit from the front
+        let behind = Ray::new(corner + Vec3::new(0.25, 0.25, -2.), Vec3::Z);
+        assert!(mesh.ray_intersection(behind, false).is_none());
+        // a triangle whose plane passes through the origin
+        let triangle = TriangleMesh::new(
+            vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            vec![Color::WHITE],
+            vec![([0, 1, 2], 0)],
+            Lambertian,
+        );
+        let ray = Ray::new(Vec3::new(0.25, 0.25, 2.), Vec3::NEG_Z);
+        let collision = triangle.ray_intersection(ray, false).unwrap();
+        assert!((collision.distance - 2.).abs() < 1e-9);
+        // starting on one side of a slab and hitting the parallel side
+        let slab = TriangleMesh::new(
+            vec![
+                Vec3::new(-1., -1., 1.),
+                Vec3::new(1., -1., 1.),
+                Vec3::new(0., 1., 1.),
+                Vec3::new(-1., -1., -1.),
+                Vec3::new(0., 1., -1.),
+                Vec3::new(1., -1., -1.),
+            ],
+            vec![Color::WHITE],
+            vec![([2, 1, 0], 0), ([5, 4, 3], 0)],
+            Lambertian,
+        );
+        let inside = Ray::new(Vec3::new(0., 0., -1.), Vec3::Z);
+        let collision = slab.ray_intersection(inside, false).unwrap();
+        assert!((collision.distance - 2.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_hit_ids() {
+        let sphere = |x: f64| Sphere::new(Vec3::new(x, 0., 0.), 0.5, Color::WHITE, Lambertian);
+        let object_id = |shape: &dyn Shape<Material = _>, x: f64| {
+            let ray = Ray::new(Vec3::new(x, 0., 5.), Vec3::NEG_Z);
+            shape.ray_intersection(ray, false).unwrap().object_id
+        };
+        assert_eq!(object_id(&sphere(0.), 0.), 0);
+        let flat = vec![sphere(0.), sphere(2.), sphere(4.)];
+        assert_eq!(object_id(&flat, 2.), 1);
+        assert_eq!(object_id(&flat, 4.), 2);
+        // nested lists number their leaves in order
+        let nested = vec![vec![sphere(0.), sphere(2.)], vec![sphere(4.)]];
+        assert_eq!(object_id(&nested, 2.), 1);
+        assert_eq!(object_id(&nested, 4.), 2);
+        let pair = Arc::new(vec![sphere(0.), sphere(2.)]);
+        let instances = vec![
+            Instance::new(pair.clone(), Transform::identity()),
+            Instance::new(pair, Transform::translation(Vec3::new(4., 0., 0.))),
+        ];
+        assert_eq!(instances.object_count(), 4);
+        assert_eq!(object_id(&instances, 2.), 1);
+        assert_eq!(object_id(&instances, 6.), 3);
+        let hits = instances.ray_intersections(Ray::new(Vec3::new(6., 0., 5.), Vec3::NEG_Z), false);
+        assert!(hits.iter().all(|collision| collision.object_id == 3));
+        // primitive ids are the triangle indices of a mesh
+        let mesh = fold();
+        let corner = mesh.vertices[0];
+        let primitive_id = |start: Vec3, dir: Vec3| {
+            let ray = Ray::new(corner + start, dir);
+            mesh.ray_intersection(ray, false).unwrap().primitive_id
+        };
+        assert_eq!(primitive_id(Vec3::new(0.25, 0.25, 2.), Vec3::NEG_Z), 0);
+        assert_eq!(primitive_id(Vec3::new(0.25, 2., 0.25), Vec3::NEG_Y), 1);
+    }
+
+    // both shapes are hit at the same distance with the same normals
+    fn assert_same_hits(a: &impl Shape, b: &impl Shape, rays: &[Ray]) {
+        let mut hit_count = 0;
+        for ray in rays {
+            let hits = (
+                a.ray_intersection(ray.clone(), false),
+                b.ray_intersection(ray.clone(), false),
+            );
+            match hits {
+                (Some(a), Some(b)) => {
+                    assert!((a.distance - b.distance).abs() < 1e-9, "{ray:?}");
+                    assert!((a.point - b.point).l1_norm() < 1e-9, "{ray:?}");
+                    assert!((a.normal - b.normal).l1_norm() < 1e-9, "{ray:?}");
+                    let shading = a.material.normal() - b.material.normal();
+                    assert!(shading.l1_norm() < 1e-9, "{ray:?}");
+                    assert_eq!(a.front_face, b.front_face);
+                    hit_count += 1;
+                }
+                (None, None) => {}
+                _ => panic!("only one shape was hit by {ray:?}"),
+            }
+        }
+        assert!(hit_count > rays.len() / 4, "{hit_count} hits");
+    }
+
+    #[test]
+    fn test_instances() {
+        let rays: Vec<_> = (0..20)
+            .map(|i| {
+                let angle = i as f64 * 0.7;
+                let start = Vec3::new(angle.cos(), angle.sin(), 0.5) * 8.;
+                let target = Vec3::new((angle * 3.).sin(), (angle * 5.).cos(), angle.sin()) * 0.6;
+                Ray::new_unit(start, target - start)
+            })
+            .collect();
+        let unit =
+            |center: Vec3, radius: f64| Sphere::new(center, radius, Color::WHITE, Lambertian);
+        let offset = Vec3::new(1., -0.5, 0.25);
+        let translated = Instance::translated(Arc::new(unit(Vec3::ZERO, 1.)), offset);
+        assert_same_hits(&translated, &unit(offset, 1.), &rays);
+        let scaled = Instance::new(
+            Arc::new(unit(Vec3::ZERO, 1.)),
+            Transform::scale(Vec3::splat(2.)),
+        );
+        assert_same_hits(&scaled, &unit(Vec3::ZERO, 2.), &rays);
+        // a non uniform scale and rotation of a unit cube is a rotated box
+        let rotation = Quaternion::from_axis_angle(Vec3::new(1., 2., 3.), 0.8);
+        let cube = Cuboid::new(Vec3::splat(-1.), Vec3::splat(1.), Color::WHITE, Lambertian);
+        let instance = Instance::new(
+            Arc::new(cube),
+            Transform::from_trs(offset, rotation, Vec3::new(2., 0.5, 1.5)),
+        );
+        let half_size = Vec3::new(2., 0.5, 1.5);
+        let cuboid = Cuboid::new(
+            offset - half_size,
+            offset + half_size,
+            Color::WHITE,
+            Lambertian,
+        )
+        .with_rotation(rotation);
+        assert_same_hits(&instance, &cuboid, &rays);
+    }
+
+    #[test]
+    fn test_instance_tolerance() {
+        let sphere = Arc::new(Sphere::new(Vec3::ZERO, 1., Color::WHITE, Lambertian));
+        // just outside a huge sphere, further than the world tolerance
+        let huge = Instance::new(sphere.clone(), Transform::scale(Vec3::splat(1e3)));
+        let ray = Ray::new(Vec3::new(0., 0., 1e3 + 2e-5), Vec3::NEG_Z);
+        let collision = huge.ray_intersection(ray, false).unwrap();
+        assert!((collision.distance - 2e-5).abs() < 1e-9);
+        // on a tiny sphere the near surface is skipped for the far one
+        let tiny = Instance::new(sphere, Transform::scale(Vec3::splat(1e-3)));
+        let ray = Ray::new(Vec3::new(0., 0., 1e-3 + 1e-7), Vec3::NEG_Z);
+        let collision = tiny.ray_intersection(ray.clone(), false).unwrap();
+        assert!((collision.distance - 2e-3).abs() < 1e-6);
+        assert_eq!(tiny.ray_intersections(ray.clone(), false).len(), 1);
+        assert_eq!(tiny.ray_intersections(ray, true).len(), 2);
+    }
+
+    #[test]
+    fn test_rotating_instance_bounds() {
+        let local = Aabb::new(Vec3::new(1., 0., -0.5), Vec3::new(2., 1., 0.5));
+        let cuboid = Arc::new(Cuboid::new(local.min, local.max, Color::WHITE, Lambertian));
+        let keyframe = |time, angle| {
+            let rotation = Quaternion::from_axis_angle(Vec3::Z, angle);
+            Keyframe::new(time, Vec3::ZERO, rotation, Vec3::splat(1.))
+        };
+        let motion = AnimatedTransform::new(vec![keyframe(0., 0.), keyframe(1., FRAC_PI_2)]);
+        let instance = Instance::animated(cuboid, motion.clone());
+        let bounds = instance.bounds().unwrap();
+        for i in 0..=16 {
+            let transform = motion.at(i as f64 / 16.);
+            for corner in local.corners().map(|corner| transform.apply_point(corner)) {
+                assert!(
+                    corner.x >= bounds.min.x - 1e-9
+                        && corner.y >= bounds.min.y - 1e-9
+                        && corner.z >= bounds.min.z - 1e-9
+                        && corner.x <= bounds.max.x + 1e-9
+                        && corner.y <= bounds.max.y + 1e-9
+                        && corner.z <= bounds.max.z + 1e-9,
+                    "{corner:?} outside {bounds:?}"
+                );
+            }
+        }
+        // halfway the far corner swings past the top of both keyframe boxes
+        let ray = Ray::new(Vec3::new(0.7, 2.05, 5.), Vec3::NEG_Z).with_time(0.5);
+        let collision = instance.ray_intersection(ray, false).unwrap();
+        assert!((collision.distance - 4.5).abs() < 1e-9);
+        assert!(bounds.max.y > 2.05);
+    }
+
+    #[test]
+    fn test_motion_blur() {
+        let sphere = Sphere::new(Vec3::ZERO, 0.5, Color::WHITE, Lambertian).with_motion(
+            Vec3::new(2., 0., 0.),
+            0.,
+            1.,
+        );
+        let ray = |x: f64, time: f64| Ray::new(Vec3::new(x, 0., 5.), Vec3::NEG_Z).with_time(time);
+        let collision = sphere.ray_intersection(ray(0., 0.), false).unwrap();
+        assert!((collision.distance - 4.5).abs() < 1e-9);
+        assert!(sphere.ray_intersection(ray(0., 1.), false).is_none());
+        assert!(sphere.ray_intersection(ray(2., 1.), false).is_some());
+        assert!(sphere.ray_intersection(ray(1., 0.5), false).is_some());
+        // resting at the end of the motion afterwards
+        assert!(sphere.ray_intersection(ray(2., 3.), false).is_some());
+        let bounds = sphere.bounds().unwrap();
+        assert_eq!(
+            (bounds.min, bounds.max),
+            (Vec3::splat(-0.5), Vec3::new(2.5, 0.5, 0.5))
+        );
+        // the same motion from keyframes
+        let keyframe = |time: f64, x: f64| {
+            Keyframe::new(
+                time,
+                Vec3::new(x, 0., 0.),
+                Quaternion::IDENTITY,
+                Vec3::splat(1.),
+            )
+        };
+        let instance = Instance::animated(
+            Arc::new(Sphere::new(Vec3::ZERO, 0.5, Color::WHITE, Lambertian)),
+            AnimatedTransform::new(vec![keyframe(0., 0.), keyframe(1., 2.)]),
+        );
+        assert!(instance.ray_intersection(ray(0., 0.), false).is_some());
+        assert!(instance.ray_intersection(ray(0., 1.), false).is_none());
+        assert!(instance.ray_intersection(ray(2., 1.), false).is_some());
+    }
+}
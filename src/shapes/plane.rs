@@ -0,0 +1,251 @@
+use crate::{
+    img::Color,
+    material::{ColorMaterial, Reflector},
+    math::{Aabb, Ray, Vec2, Vec3},
+    texture::Texture,
+    EPSILON,
+};
+
+use super::{Collision, Shape};
+
+// distance along the ray to the plane through point, None when parallel or behind the start
+pub(super) fn plane_distance(
+    ray: &Ray,
+    point: Vec3,
+    normal: Vec3,
+    include_start: bool,
+) -> Option<f64> {
+    let facing = ray.dir.dot(normal);
+    if facing.abs() <= EPSILON {
+        return None;
+    }
+    let distance = (point - ray.start).dot(normal) / facing;
+    if distance <= -EPSILON || (!include_start && distance <= EPSILON) {
+        return None;
+    }
+    Some(distance)
+}
+
+// both sides are solid, the material normal faces the incoming ray
+pub(super) fn two_sided_collision<R: Reflector + Clone>(
+    ray: &Ray,
+    distance: f64,
+    normal: Vec3,
+    color: Color,
+    reflector: &R,
+    uv: Vec2,
+) -> Collision<ColorMaterial<R>> {
+    let facing_normal = if ray.dir.dot(normal) < 0. {
+        normal
+    } else {
+        -normal
+    };
+    Collision::new(
+        ray,
+        distance,
+        normal,
+        ColorMaterial::new(facing_normal, color, reflector.clone()),
+    )
+    .with_uv(uv)
+}
+
+/// Infinite two sided plane, uvs are distances along two axes in the plane
+#[derive(Debug, Clone)]
+pub struct Plane<R: Reflector + Clone, T: Texture = Color> {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub u_axis: Vec3,
+    pub v_axis: Vec3,
+    pub color: T,
+    pub reflector: R,
+}
+
+impl<R: Reflector + Clone, T: Texture> Plane<R, T> {
+    pub fn new(point: Vec3, normal: Vec3, color: T, reflector: R) -> Self {
+        let normal = normal.normalize();
+        let helper = if normal.x.abs() < 0.9 {
+            Vec3::X
+        } else {
+            Vec3::Y
+        };
+        let u_axis = helper.reject_wrt(normal).normalize();
+        Self {
+            point,
+            normal,
+            u_axis,
+            v_axis: normal.cross(u_axis),
+            color,
+            reflector,
+        }
+    }
+
+    // u_axis is projected into the plane
+    pub fn with_u_axis(mut self, u_axis: Vec3) -> Self {
+        self.u_axis = u_axis.reject_wrt(self.normal).normalize();
+        self.v_axis = self.normal.cross(self.u_axis);
+        self
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> Shape for Plane<R, T> {
+    type Material = ColorMaterial<R>;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        let distance = plane_distance(&ray, self.point, self.normal, include_start)?;
+        let point = ray.point_at(distance);
+        let relative = point - self.point;
+        let uv = Vec2::new(relative.dot(self.u_axis), relative.dot(self.v_axis));
+        let color = self.color.sample(uv, point);
+        Some(two_sided_collision(
+            &ray,
+            distance,
+            self.normal,
+            color,
+            &self.reflector,
+            uv,
+        ))
+    }
+}
+
+/// The parallelogram spanned by u and v from corner, with uvs from 0 to 1 along them.
+/// The normal follows the right hand rule from u to v
+#[derive(Debug, Clone)]
+pub struct Quad<R: Reflector + Clone, T: Texture = Color> {
+    pub corner: Vec3,
+    // the edges, which the normal and w are derived from
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    pub color: T,
+    pub reflector: R,
+    // solves for the uv of a point in the plane
+    w: Vec3,
+}
+
+impl<R: Reflector + Clone, T: Texture> Quad<R, T> {
+    pub fn new(corner: Vec3, u: Vec3, v: Vec3, color: T, reflector: R) -> Self {
+        let n = u.cross(v);
+        Self {
+            corner,
+            u,
+            v,
+            normal: n.normalize(),
+            color,
+            reflector,
+            w: n / n.dot(n),
+        }
+    }
+
+    pub fn u(&self) -> Vec3 {
+        self.u
+    }
+
+    pub fn v(&self) -> Vec3 {
+        self.v
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> Shape for Quad<R, T> {
+    type Material = ColorMaterial<R>;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        let distance = plane_distance(&ray, self.corner, self.normal, include_start)?;
+        let point = ray.point_at(distance);
+        let relative = point - self.corner;
+        let uv = Vec2::new(
+            self.w.dot(relative.cross(self.v)),
+            self.w.dot(self.u.cross(relative)),
+        );
+        if !(0.0..=1.).contains(&uv.x) || !(0.0..=1.).contains(&uv.y) {
+            return None;
+        }
+        let color = self.color.sample(uv, point);
+        Some(two_sided_collision(
+            &ray,
+            distance,
+            self.normal,
+            color,
+            &self.reflector,
+            uv,
+        ))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points([
+            self.corner,
+            self.corner + self.u,
+            self.corner + self.v,
+            self.corner + self.u + self.v,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        img::Color,
+        material::{Lambertian, Material},
+        math::{Ray, Vec3},
+        shapes::Shape,
+    };
+
+    use super::{Plane, Quad};
+
+    #[test]
+    fn test_plane_sides() {
+        let plane = Plane::new(Vec3::new(0., 1., 0.), Vec3::Y, Color::WHITE, Lambertian);
+        let from_above = Ray::new(Vec3::new(3., 4., 0.), Vec3::NEG_Y);
+        let collision = plane.ray_intersection(from_above, false).unwrap();
+        assert_eq!(collision.distance, 3.);
+        assert!(collision.front_face);
+        assert_eq!(collision.material.normal(), Vec3::Y);
+        let from_below = Ray::new(Vec3::new(3., -4., 0.), Vec3::Y);
+        let collision = plane.ray_intersection(from_below, false).unwrap();
+        assert!(!collision.front_face);
+        assert_eq!(collision.normal, Vec3::Y);
+        assert_eq!(collision.material.normal(), Vec3::NEG_Y);
+        assert!(plane
+            .ray_intersection(Ray::new(Vec3::ZERO, Vec3::X), true)
+            .is_none());
+    }
+
+    #[test]
+    fn test_plane_include_start() {
+        let plane = Plane::new(Vec3::ZERO, Vec3::Y, Color::WHITE, Lambertian);
+        let on_surface = Ray::new(Vec3::new(1., 0., 1.), Vec3::new(0., -1., 1.).normalize());
+        assert!(plane.ray_intersection(on_surface.clone(), false).is_none());
+        assert_eq!(
+            plane.ray_intersection(on_surface, true).unwrap().distance,
+            0.
+        );
+    }
+
+    #[test]
+    fn test_quad_bounds_and_uv() {
+        let quad = Quad::new(
+            Vec3::new(-1., 0., -1.),
+            Vec3::new(2., 0., 0.),
+            Vec3::new(0., 0., 4.),
+            Color::WHITE,
+            Lambertian,
+        );
+        assert_eq!(quad.normal(), Vec3::NEG_Y);
+        assert_eq!(
+            (quad.u(), quad.v()),
+            (Vec3::new(2., 0., 0.), Vec3::new(0., 0., 4.))
+        );
+        let hit = |x, z| quad.ray_intersection(Ray::new(Vec3::new(x, 2., z), Vec3::NEG_Y), false);
+        let collision = hit(0.5, 2.).unwrap();
+        assert!((collision.uv.x - 0.75).abs() < 1e-12 && (collision.uv.y - 0.75).abs() < 1e-12);
+        assert!(!collision.front_face);
+        assert!(hit(1.5, 0.).is_none());
+        assert!(hit(0., -1.5).is_none());
+        let bounds = quad.bounds().unwrap();
+        assert_eq!(bounds.min, Vec3::new(-1., 0., -1.));
+        assert_eq!(bounds.max, Vec3::new(1., 0., 3.));
+    }
+}
@@ -0,0 +1,413 @@
+use crate::{
+    img::Color,
+    material::{ColorMaterial, Reflector},
+    math::{Aabb, Ray, Vec2, Vec3},
+    texture::Texture,
+};
+
+use super::{
+    plane::{plane_distance, two_sided_collision},
+    Collision, ColorIndex, Shape, TriangleMesh, VertexIndex,
+};
+
+/// A flat two sided polygon from a loop of vertices, which may be concave and have holes.
+/// Uvs span the bounding rectangle of the outline within its plane
+#[derive(Debug, Clone)]
+pub struct Polygon<R: Reflector + Clone, T: Texture = Color> {
+    pub outline: Vec<Vec3>,
+    pub holes: Vec<Vec<Vec3>>,
+    // counterclockwise winding of the outline seen from this side
+    pub normal: Vec3,
+    pub color: T,
+    pub reflector: R,
+    u_axis: Vec3,
+    v_axis: Vec3,
+    // the outline and then the holes, projected onto the axes
+    rings: Vec<Vec<Vec2>>,
+    uv_min: Vec2,
+    uv_size: Vec2,
+}
+
+impl<R: Reflector + Clone, T: Texture> Polygon<R, T> {
+    // vertices off the plane of the outline are projected onto it
+    pub fn new(outline: Vec<Vec3>, color: T, reflector: R) -> Self {
+        assert!(outline.len() >= 3, "Expected at least three vertices");
+        let origin = outline[0];
+        let normal = outline
+            .iter()
+            .zip(outline.iter().cycle().skip(1))
+            .fold(Vec3::ZERO, |sum, (&a, &b)| {
+                sum + (a - origin).cross(b - origin)
+            });
+        assert!(
+            normal.magnitude() > 0.,
+            "Expected the outline to enclose an area, got {outline:?}"
+        );
+        let normal = normal.normalize();
+        let u_axis = outline
+            .iter()
+            .map(|&vertex| (vertex - origin).reject_wrt(normal))
+            .find(|edge| edge.magnitude() > 0.)
+            .unwrap()
+            .normalize();
+        let v_axis = normal.cross(u_axis);
+        let mut polygon = Self {
+            outline,
+            holes: vec![],
+            normal,
+            color,
+            reflector,
+            u_axis,
+            v_axis,
+            rings: vec![],
+            uv_min: Vec2::ZERO,
+            uv_size: Vec2::ZERO,
+        };
+        let ring = polygon.project_ring(&polygon.outline);
+        let (min, max) = ring.iter().fold(
+            (Vec2::new(f64::MAX, f64::MAX), Vec2::new(f64::MIN, f64::MIN)),
+            |(min, max), p| {
+                (
+                    Vec2::new(min.x.min(p.x), min.y.min(p.y)),
+                    Vec2::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            },
+        );
+        polygon.rings.push(ring);
+        polygon.uv_min = min;
+        polygon.uv_size = max - min;
+        polygon
+    }
+
+    pub fn with_hole(mut self, hole: Vec<Vec3>) -> Self {
+        assert!(hole.len() >= 3, "Expected at least three vertices");
+        let ring = self.project_ring(&hole);
+        assert!(
+            ring.iter()
+                .all(|&point| ring_contains(&self.rings[0], point)),
+            "Expected the hole to lie inside the outline, got {hole:?}"
+        );
+        self.rings.push(ring);
+        self.holes.push(hole);
+        self
+    }
+
+    fn project(&self, point: Vec3) -> Vec2 {
+        let relative = point - self.outline[0];
+        Vec2::new(relative.dot(self.u_axis), relative.dot(self.v_axis))
+    }
+
+    fn project_ring(&self, ring: &[Vec3]) -> Vec<Vec2> {
+        ring.iter().map(|&vertex| self.project(vertex)).collect()
+    }
+
+    // even-odd rule over all rings, so holes cut out of the outline
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.rings
+            .iter()
+            .filter(|ring| ring_contains(ring, point))
+            .count()
+            % 2
+            == 1
+    }
+
+    fn uv(&self, point: Vec2) -> Vec2 {
+        let relative = point - self.uv_min;
+        Vec2::new(relative.x / self.uv_size.x, relative.y / self.uv_size.y)
+    }
+
+    /// Ear clips the polygon into a mesh facing along the normal, holes are first joined to the
+    /// outline through bridges
+    pub fn triangulate(&self) -> TriangleMesh<R, T>
+    where
+        T: Clone,
+    {
+        let vertices: Vec<Vec3> = self
+            .outline
+            .iter()
+            .chain(self.holes.iter().flatten())
+            .copied()
+            .collect();
+        assert!(
+            vertices.len() <= VertexIndex::MAX as usize,
+            "Too many vertices for a mesh"
+        );
+        let points: Vec<Vec2> = self.rings.iter().flatten().copied().collect();
+        let mut ring: Vec<usize> = (0..self.outline.len()).collect();
+        if signed_area(&points, &ring) < 0. {
+            ring.reverse();
+        }
+        let mut holes: Vec<Vec<usize>> = vec![];
+        let mut offset = self.outline.len();
+        for hole in &self.holes {
+            let mut indices: Vec<usize> = (offset..offset + hole.len()).collect();
+            if signed_area(&points, &indices) > 0. {
+                indices.reverse();
+            }
+            offset += hole.len();
+            holes.push(indices);
+        }
+        // bridging the rightmost hole first keeps later bridges from crossing it
+        holes.sort_by(|a, b| rightmost(&points, b).total_cmp(&rightmost(&points, a)));
+        for hole in holes {
+            bridge_hole(&points, &mut ring, &hole);
+        }
+        let triangles = ear_clip(&points, ring)
+            .into_iter()
+            .filter(|&[a, b, c]| (points[b] - points[a]).cross(points[c] - points[b]).abs() > 1e-12)
+            .map(|corners| (corners.map(|i| i as VertexIndex), 0 as ColorIndex))
+            .collect();
+        let uvs = points.iter().map(|&point| self.uv(point)).collect();
+        TriangleMesh::new(
+            vertices,
+            vec![self.color.clone()],
+            triangles,
+            self.reflector.clone(),
+        )
+        .with_uvs(uvs)
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> Shape for Polygon<R, T> {
+    type Material = ColorMaterial<R>;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        let distance = plane_distance(&ray, self.outline[0], self.normal, include_start)?;
+        let point = ray.point_at(distance);
+        let projected = self.project(point);
+        if !self.contains(projected) {
+            return None;
+        }
+        let uv = self.uv(projected);
+        let color = self.color.sample(uv, point);
+        Some(two_sided_collision(
+            &ray,
+            distance,
+            self.normal,
+            color,
+            &self.reflector,
+            uv,
+        ))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.outline.iter().copied())
+    }
+}
+
+fn ring_contains(ring: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (&a, &b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+        if (a.y > point.y) != (b.y > point.y) {
+            let x = a.x + (point.y - a.y) * (b.x - a.x) / (b.y - a.y);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+// positive for counterclockwise rings
+fn signed_area(points: &[Vec2], ring: &[usize]) -> f64 {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(&a, &b)| points[a].cross(points[b]))
+        .sum::<f64>()
+        / 2.
+}
+
+fn rightmost(points: &[Vec2], ring: &[usize]) -> f64 {
+    ring.iter().map(|&i| points[i].x).fold(f64::MIN, f64::max)
+}
+
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    (b - a).cross(p - a) >= 0. && (c - b).cross(p - b) >= 0. && (a - c).cross(p - c) >= 0.
+}
+
+// splices a clockwise hole into the counterclockwise ring through a pair of coincident edges
+// from its rightmost vertex to a vertex of the ring it can see, after David Eberly
+fn bridge_hole(points: &[Vec2], ring: &mut Vec<usize>, hole: &[usize]) {
+    let start = (0..hole.len())
+        .max_by(|&a, &b| points[hole[a]].x.total_cmp(&points[hole[b]].x))
+        .unwrap();
+    let m = points[hole[start]];
+    // the nearest edge crossed by a ray from m towards +x
+    let mut nearest: Option<(f64, usize)> = None;
+    for k in 0..ring.len() {
+        let (a, b) = (points[ring[k]], points[ring[(k + 1) % ring.len()]]);
+        if (a.y > m.y) == (b.y > m.y) {
+            continue;
+        }
+        let x = a.x + (m.y - a.y) * (b.x - a.x) / (b.y - a.y);
+        if x >= m.x && nearest.is_none_or(|(nearest_x, _)| x < nearest_x) {
+            let end = if a.x > b.x { k } else { (k + 1) % ring.len() };
+            nearest = Some((x, end));
+        }
+    }
+    let (x, mut bridge) = nearest.expect("Expected every hole to lie inside the outline");
+    // a ring vertex inside the triangle from m to the hit could block the bridge,
+    // the one closest in angle to the ray is visible
+    let hit = Vec2::new(x, m.y);
+    let candidate = points[ring[bridge]];
+    let (a, b) = if candidate.y < m.y {
+        (candidate, hit)
+    } else {
+        (hit, candidate)
+    };
+    let mut best = None;
+    for (k, &index) in ring.iter().enumerate() {
+        let p = points[index];
+        if k == bridge || p.x < m.x || !in_triangle(p, m, a, b) {
+            continue;
+        }
+        let offset = p - m;
+        let key = (offset.x / offset.dot(offset).sqrt(), -offset.dot(offset));
+        if best.is_none_or(|(best_key, _)| key > best_key) {
+            best = Some((key, k));
+        }
+    }
+    if let Some((_, k)) = best {
+        bridge = k;
+    }
+    let spliced = hole[start..]
+        .iter()
+        .chain(&hole[..=start])
+        .copied()
+        .chain([ring[bridge]]);
+    ring.splice(bridge + 1..bridge + 1, spliced.collect::<Vec<_>>());
+}
+
+fn ear_clip(points: &[Vec2], mut ring: Vec<usize>) -> Vec<[usize; 3]> {
+    let mut triangles = Vec::with_capacity(ring.len().saturating_sub(2));
+    let (mut k, mut stalled) = (0, 0);
+    while ring.len() > 3 {
+        let n = ring.len();
+        let [prev, current, next] = [ring[(k + n - 1) % n], ring[k], ring[(k + 1) % n]];
+        // a ring that is not simple has no ears left, so its vertices are clipped anyway
+        if stalled > n || is_ear(points, &ring, [prev, current, next]) {
+            triangles.push([prev, current, next]);
+            ring.remove(k);
+            stalled = 0;
+        } else {
+            k += 1;
+            stalled += 1;
+        }
+        k %= ring.len();
+    }
+    triangles.push([ring[0], ring[1], ring[2]]);
+    triangles
+}
+
+fn is_ear(points: &[Vec2], ring: &[usize], [prev, current, next]: [usize; 3]) -> bool {
+    let (a, b, c) = (points[prev], points[current], points[next]);
+    if (b - a).cross(c - b) <= 0. {
+        return false;
+    }
+    // bridges duplicate vertices, which touch the ear without blocking it
+    !ring.iter().any(|&i| {
+        let p = points[i];
+        p != a && p != b && p != c && in_triangle(p, a, b, c)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        img::Color,
+        material::Lambertian,
+        math::{Ray, Vec3},
+        shapes::Shape,
+    };
+
+    use super::Polygon;
+
+    // an L shape in the floor with a square hole in its long arm
+    fn polygon() -> Polygon<Lambertian> {
+        let floor = |x, z| Vec3::new(x, 0., z);
+        Polygon::new(
+            vec![
+                floor(0., 0.),
+                floor(3., 0.),
+                floor(3., -1.),
+                floor(1., -1.),
+                floor(1., -4.),
+                floor(0., -4.),
+            ],
+            Color::WHITE,
+            Lambertian,
+        )
+        .with_hole(vec![
+            floor(0.25, -2.25),
+            floor(0.75, -2.25),
+            floor(0.75, -2.75),
+            floor(0.25, -2.75),
+        ])
+    }
+
+    fn area(mesh_vertices: &[Vec3], triangles: &[[u16; 3]]) -> f64 {
+        triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                let [a, b, c] = [a, b, c].map(|i| mesh_vertices[i as usize]);
+                (b - a).cross(c - a).magnitude() / 2.
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_concave_with_hole() {
+        let polygon = polygon();
+        assert_eq!(polygon.normal, Vec3::Y);
+        let hit =
+            |x, z| polygon.ray_intersection(Ray::new(Vec3::new(x, 1., z), Vec3::NEG_Y), false);
+        assert!(hit(0.5, -3.5).is_some());
+        assert!(hit(2.5, -0.5).is_some());
+        // in the notch of the L and in the hole
+        assert!(hit(2., -2.).is_none());
+        assert!(hit(0.5, -2.5).is_none());
+        for (x, z) in [(0.01, -0.01), (2.99, -0.99), (0.01, -3.99)] {
+            let uv = hit(x, z).unwrap().uv;
+            assert!(
+                (0.0..=1.).contains(&uv.x) && (0.0..=1.).contains(&uv.y),
+                "{uv:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_triangulate() {
+        let polygon = polygon();
+        let mesh = polygon.triangulate();
+        // two arms of 3 minus the hole of 0.25
+        assert!((area(&mesh.vertices, &mesh.triangles) - 5.75).abs() < 1e-9);
+        assert!(mesh.normals.iter().all(|&normal| normal == Vec3::Y));
+        for (x, z, inside) in [
+            (0.5, -3.5, true),
+            (2.5, -0.5, true),
+            (2., -2., false),
+            (0.5, -2.5, false),
+        ] {
+            let ray = Ray::new(Vec3::new(x, 1., z), Vec3::NEG_Y);
+            assert_eq!(
+                mesh.ray_intersection(ray, false).is_some(),
+                inside,
+                "{x} {z}"
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "enclose an area")]
+    fn test_collinear_outline() {
+        let points = vec![Vec3::ZERO, Vec3::X, 2. * Vec3::X];
+        Polygon::new(points, Color::WHITE, Lambertian);
+    }
+
+    #[test]
+    #[should_panic(expected = "inside the outline")]
+    fn test_hole_outside() {
+        let floor = |x, z| Vec3::new(x, 0., z);
+        polygon().with_hole(vec![floor(4., -2.), floor(5., -2.), floor(5., -3.)]);
+    }
+}
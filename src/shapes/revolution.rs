@@ -0,0 +1,478 @@
+use std::f64::consts::TAU;
+
+use crate::{
+    img::Color,
+    material::{ColorMaterial, Reflector},
+    math::{solve_quadratic, solve_quartic, Aabb, Mat3x3, Ray, Transform, Vec2, Vec3},
+    texture::Texture,
+    EPSILON,
+};
+
+use super::{plane::two_sided_collision, Collision, Shape};
+
+fn is_valid_distance(distance: f64, include_start: bool) -> bool {
+    distance > -EPSILON && (include_start || distance > EPSILON)
+}
+
+// world to a local frame with origin at the origin and axis along +z
+fn axis_frame(origin: Vec3, axis: Vec3) -> Transform {
+    let axis = axis.normalize();
+    let helper = if axis.x.abs() < 0.9 { Vec3::X } else { Vec3::Y };
+    let tangent = helper.reject_wrt(axis).normalize();
+    let rotation = Mat3x3::from_row_vectors(tangent, axis.cross(tangent), axis);
+    Transform::from_affine(&rotation, -(&rotation * origin)).unwrap()
+}
+
+// the angle around the local z axis, from 0 to 1
+fn azimuth(point: Vec3) -> f64 {
+    (point.y.atan2(point.x) / TAU).rem_euclid(1.)
+}
+
+// the box around a circle of radius around center, perpendicular to axis
+fn circle_bounds(center: Vec3, axis: Vec3, radius: f64) -> Aabb {
+    let axis = axis.normalize();
+    let extent = Vec3::new(
+        (1. - axis.x * axis.x).max(0.).sqrt(),
+        (1. - axis.y * axis.y).max(0.).sqrt(),
+        (1. - axis.z * axis.z).max(0.).sqrt(),
+    ) * radius;
+    Aabb::new(center - extent, center + extent)
+}
+
+// distance, local normal and uv of a hit in the local frame
+type LocalHit = (f64, Vec3, Vec2);
+
+/// A cone cut off perpendicular to its axis at base and top, radii may be zero for an apex.
+/// Uvs go around the axis and from base to top, the caps map radially
+#[derive(Debug, Clone)]
+pub struct Cone<R: Reflector + Clone, T: Texture = Color> {
+    pub base: Vec3,
+    pub top: Vec3,
+    pub base_radius: f64,
+    pub top_radius: f64,
+    // closed cones are solids with normals facing out, open ones are two sided
+    pub capped: bool,
+    pub color: T,
+    pub reflector: R,
+    to_local: Transform,
+}
+
+impl<R: Reflector + Clone, T: Texture> Cone<R, T> {
+    pub fn new(
+        base: Vec3,
+        base_radius: f64,
+        top: Vec3,
+        top_radius: f64,
+        color: T,
+        reflector: R,
+    ) -> Self {
+        Self {
+            base,
+            top,
+            base_radius,
+            top_radius,
+            capped: false,
+            color,
+            reflector,
+            to_local: axis_frame(base, top - base),
+        }
+    }
+
+    pub fn with_caps(mut self, capped: bool) -> Self {
+        self.capped = capped;
+        self
+    }
+
+    fn height(&self) -> f64 {
+        (self.top - self.base).magnitude()
+    }
+
+    fn side_hits<'a>(
+        &'a self,
+        ray: &'a Ray,
+        height: f64,
+        include_start: bool,
+    ) -> impl Iterator<Item = LocalHit> + 'a {
+        let (o, d) = (ray.start, ray.dir);
+        // the radius grows by slope per unit of height
+        let slope = (self.top_radius - self.base_radius) / height;
+        let start_radius = self.base_radius + slope * o.z;
+        let a = d.x * d.x + d.y * d.y - slope * slope * d.z * d.z;
+        let b = 2. * (o.x * d.x + o.y * d.y - slope * d.z * start_radius);
+        let c = o.x * o.x + o.y * o.y - start_radius * start_radius;
+        let roots = solve_quadratic(a, b, c).map_or(vec![], |(t0, t1)| vec![t0, t1]);
+        roots.into_iter().filter_map(move |distance| {
+            let point = ray.point_at(distance);
+            if !is_valid_distance(distance, include_start) || !(0.0..=height).contains(&point.z) {
+                return None;
+            }
+            let radius = self.base_radius + slope * point.z;
+            let normal = Vec3::new(point.x, point.y, -slope * radius);
+            Some((
+                distance,
+                normal,
+                Vec2::new(azimuth(point), point.z / height),
+            ))
+        })
+    }
+
+    fn cap_hit(&self, ray: &Ray, z: f64, radius: f64, include_start: bool) -> Option<LocalHit> {
+        if !self.capped || radius <= 0. || ray.dir.z == 0. {
+            return None;
+        }
+        let distance = (z - ray.start.z) / ray.dir.z;
+        let point = ray.point_at(distance);
+        let rho = (point.x * point.x + point.y * point.y).sqrt();
+        if !is_valid_distance(distance, include_start) || rho > radius {
+            return None;
+        }
+        let normal = if z == 0. { Vec3::NEG_Z } else { Vec3::Z };
+        Some((distance, normal, Vec2::new(azimuth(point), rho / radius)))
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> Shape for Cone<R, T> {
+    type Material = ColorMaterial<R>;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        let local = self.to_local.apply_ray(&ray);
+        let height = self.height();
+        let (distance, normal, uv) = self
+            .side_hits(&local, height, include_start)
+            .chain(self.cap_hit(&local, 0., self.base_radius, include_start))
+            .chain(self.cap_hit(&local, height, self.top_radius, include_start))
+            .min_by(|a, b| a.0.total_cmp(&b.0))?;
+        let normal = self
+            .to_local
+            .inverse_matrix()
+            .transform_vector(normal)
+            .normalize();
+        let color = self.color.sample(uv, ray.point_at(distance));
+        if !self.capped {
+            return Some(two_sided_collision(
+                &ray,
+                distance,
+                normal,
+                color,
+                &self.reflector,
+                uv,
+            ));
+        }
+        Some(
+            Collision::new(
+                &ray,
+                distance,
+                normal,
+                ColorMaterial::new(normal, color, self.reflector.clone()),
+            )
+            .with_uv(uv),
+        )
+    }
+
+    fn is_closed(&self) -> bool {
+        self.capped
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let axis = self.top - self.base;
+        Some(
+            circle_bounds(self.base, axis, self.base_radius).union(&circle_bounds(
+                self.top,
+                axis,
+                self.top_radius,
+            )),
+        )
+    }
+}
+
+/// A cone with equal radii at both ends
+#[derive(Debug, Clone)]
+pub struct Cylinder<R: Reflector + Clone, T: Texture = Color>(Cone<R, T>);
+
+impl<R: Reflector + Clone, T: Texture> Cylinder<R, T> {
+    pub fn new(base: Vec3, top: Vec3, radius: f64, color: T, reflector: R) -> Self {
+        Self(Cone::new(base, radius, top, radius, color, reflector))
+    }
+
+    pub fn with_caps(self, capped: bool) -> Self {
+        Self(self.0.with_caps(capped))
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> Shape for Cylinder<R, T> {
+    type Material = ColorMaterial<R>;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        self.0.ray_intersection(ray, include_start)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.0.bounds()
+    }
+}
+
+/// A two sided disk, or an annulus with a nonzero inner radius.
+/// Uvs go around the normal and outwards from the inner radius
+#[derive(Debug, Clone)]
+pub struct Disk<R: Reflector + Clone, T: Texture = Color> {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub radius: f64,
+    pub inner_radius: f64,
+    pub color: T,
+    pub reflector: R,
+    to_local: Transform,
+}
+
+impl<R: Reflector + Clone, T: Texture> Disk<R, T> {
+    pub fn new(center: Vec3, normal: Vec3, radius: f64, color: T, reflector: R) -> Self {
+        Self {
+            center,
+            normal: normal.normalize(),
+            radius,
+            inner_radius: 0.,
+            color,
+            reflector,
+            to_local: axis_frame(center, normal),
+        }
+    }
+
+    pub fn with_inner_radius(mut self, inner_radius: f64) -> Self {
+        self.inner_radius = inner_radius;
+        self
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> Shape for Disk<R, T> {
+    type Material = ColorMaterial<R>;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        let local = self.to_local.apply_ray(&ray);
+        if local.dir.z.abs() <= EPSILON {
+            return None;
+        }
+        let distance = -local.start.z / local.dir.z;
+        let point = local.point_at(distance);
+        let rho = (point.x * point.x + point.y * point.y).sqrt();
+        if !is_valid_distance(distance, include_start)
+            || rho > self.radius
+            || rho < self.inner_radius
+        {
+            return None;
+        }
+        let uv = Vec2::new(
+            azimuth(point),
+            (rho - self.inner_radius) / (self.radius - self.inner_radius),
+        );
+        let color = self.color.sample(uv, ray.point_at(distance));
+        Some(two_sided_collision(
+            &ray,
+            distance,
+            self.normal,
+            color,
+            &self.reflector,
+            uv,
+        ))
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(circle_bounds(self.center, self.normal, self.radius))
+    }
+}
+
+/// A ring shaped solid around axis, the tube of minor radius sweeps a circle of major radius.
+/// Uvs go around the axis and then around the tube
+#[derive(Debug, Clone)]
+pub struct Torus<R: Reflector + Clone, T: Texture = Color> {
+    pub center: Vec3,
+    pub axis: Vec3,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+    pub color: T,
+    pub reflector: R,
+    to_local: Transform,
+}
+
+impl<R: Reflector + Clone, T: Texture> Torus<R, T> {
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+        color: T,
+        reflector: R,
+    ) -> Self {
+        Self {
+            center,
+            axis: axis.normalize(),
+            major_radius,
+            minor_radius,
+            color,
+            reflector,
+            to_local: axis_frame(center, axis),
+        }
+    }
+}
+
+impl<R: Reflector + Clone, T: Texture> Shape for Torus<R, T> {
+    type Material = ColorMaterial<R>;
+
+    fn ray_intersection(&self, ray: Ray, include_start: bool) -> Option<Collision<Self::Material>> {
+        let local = self.to_local.apply_ray(&ray);
+        let (major, minor) = (self.major_radius, self.minor_radius);
+        let d = local.dir;
+        let dd = d.dot(d);
+        // starting the quartic close to the torus keeps its coefficients well conditioned
+        let outer = major + minor;
+        let skip = (-local.start.dot(d) / dd - outer / dd.sqrt()).max(0.);
+        let o = local.point_at(skip);
+        let od = o.dot(d);
+        let k = o.dot(o) + major * major - minor * minor;
+        let r2 = 4. * major * major;
+        let (a, b, c, e) = (
+            4. * od / dd,
+            (4. * od * od + 2. * dd * k - r2 * (d.x * d.x + d.y * d.y)) / (dd * dd),
+            (4. * od * k - 2. * r2 * (o.x * d.x + o.y * d.y)) / (dd * dd),
+            (k * k - r2 * (o.x * o.x + o.y * o.y)) / (dd * dd),
+        );
+        let distance = solve_quartic(a, b, c, e)
+            .into_iter()
+            .map(|root| root + skip)
+            .find(|&distance| is_valid_distance(distance, include_start))?;
+        let point = local.point_at(distance);
+        let rho = (point.x * point.x + point.y * point.y).sqrt();
+        let towards_tube = point.dot(point) + major * major - minor * minor - 2. * major * major;
+        let normal = Vec3::new(
+            point.x * towards_tube,
+            point.y * towards_tube,
+            point.z * (towards_tube + 2. * major * major),
+        );
+        let normal = self
+            .to_local
+            .inverse_matrix()
+            .transform_vector(normal)
+            .normalize();
+        let uv = Vec2::new(
+            azimuth(point),
+            (point.z.atan2(rho - major) / TAU).rem_euclid(1.),
+        );
+        let color = self.color.sample(uv, ray.point_at(distance));
+        Some(
+            Collision::new(
+                &ray,
+                distance,
+                normal,
+                ColorMaterial::new(normal, color, self.reflector.clone()),
+            )
+            .with_uv(uv),
+        )
+    }
+
+    fn is_closed(&self) -> bool {
+        true
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let ring = circle_bounds(self.center, self.axis, self.major_radius);
+        let tube = Vec3::splat(self.minor_radius);
+        Some(Aabb::new(ring.min - tube, ring.max + tube))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        img::Color,
+        material::{Lambertian, Material},
+        math::{assert_close, Ray, Vec3},
+        shapes::Shape,
+    };
+
+    use super::{Cone, Cylinder, Disk, Torus};
+
+    #[test]
+    fn test_cylinder_side_and_caps() {
+        let cylinder = Cylinder::new(
+            Vec3::new(1., 0., 0.),
+            Vec3::new(1., 2., 0.),
+            0.5,
+            Color::WHITE,
+            Lambertian,
+        );
+        let side = Ray::new(Vec3::new(-2., 1., 0.), Vec3::X);
+        let collision = cylinder.ray_intersection(side.clone(), false).unwrap();
+        assert!((collision.distance - 2.5).abs() < 1e-9);
+        assert_close(collision.normal, Vec3::NEG_X);
+        assert!((collision.uv.y - 0.5).abs() < 1e-9);
+        let down = Ray::new(Vec3::new(1.2, 5., 0.), Vec3::NEG_Y);
+        // open cylinders let the ray through to the inside of the far wall
+        assert!(cylinder.ray_intersection(down.clone(), false).is_none());
+        let capped = cylinder.with_caps(true);
+        let collision = capped.ray_intersection(down, false).unwrap();
+        assert!((collision.distance - 3.).abs() < 1e-9);
+        assert_close(collision.normal, Vec3::Y);
+        // leaving the solid from the inside
+        let inside = Ray::new(Vec3::new(1., 1., 0.), Vec3::Z);
+        let collision = capped.ray_intersection(inside, false).unwrap();
+        assert!((collision.distance - 0.5).abs() < 1e-9);
+        assert!(!collision.front_face);
+        let bounds = capped.bounds().unwrap();
+        assert_close(bounds.min, Vec3::new(0.5, 0., -0.5));
+        assert_close(bounds.max, Vec3::new(1.5, 2., 0.5));
+    }
+
+    #[test]
+    fn test_cone_normal() {
+        let cone = Cone::new(Vec3::ZERO, 1., Vec3::Y, 0., Color::WHITE, Lambertian);
+        let ray = Ray::new(Vec3::new(-3., 0.5, 0.), Vec3::X);
+        let collision = cone.ray_intersection(ray, false).unwrap();
+        assert!((collision.distance - 2.5).abs() < 1e-9);
+        assert_close(collision.normal, Vec3::new(-1., 1., 0.).normalize());
+        assert!(cone
+            .ray_intersection(Ray::new(Vec3::new(-3., 1.5, 0.), Vec3::X), false)
+            .is_none());
+    }
+
+    #[test]
+    fn test_annulus() {
+        let disk =
+            Disk::new(Vec3::ZERO, Vec3::Z, 2., Color::WHITE, Lambertian).with_inner_radius(1.);
+        let hit = |x| disk.ray_intersection(Ray::new(Vec3::new(x, 0., -1.), Vec3::Z), false);
+        assert!(hit(0.5).is_none());
+        assert!(hit(2.5).is_none());
+        let collision = hit(1.5).unwrap();
+        assert!((collision.uv.y - 0.5).abs() < 1e-9);
+        assert_close(collision.material.normal(), Vec3::NEG_Z);
+    }
+
+    #[test]
+    fn test_torus() {
+        let torus = Torus::new(
+            Vec3::new(0., 0., 100.),
+            Vec3::Y,
+            2.,
+            0.5,
+            Color::WHITE,
+            Lambertian,
+        );
+        let ray = Ray::new(Vec3::new(-10., 0., 100.), Vec3::X);
+        let collision = torus.ray_intersection(ray.clone(), false).unwrap();
+        assert!((collision.distance - 7.5).abs() < 1e-6);
+        assert_close(collision.normal, Vec3::NEG_X);
+        // the next hit is on the inner side of the tube
+        let collision = torus.ray_intersection(ray.translate(8.), false).unwrap();
+        assert!((collision.distance - 0.5).abs() < 1e-6);
+        assert_close(collision.normal, Vec3::X);
+        // through the hole
+        let down = Ray::new(Vec3::new(0., 5., 100.), Vec3::NEG_Y);
+        assert!(torus.ray_intersection(down, false).is_none());
+        let top = Ray::new(Vec3::new(2., 5., 100.), Vec3::NEG_Y);
+        let collision = torus.ray_intersection(top, false).unwrap();
+        assert!((collision.distance - 4.5).abs() < 1e-6);
+        assert_close(collision.normal, Vec3::Y);
+    }
+}
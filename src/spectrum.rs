@@ -0,0 +1,268 @@
+use std::{
+    ops::{Add, Mul},
+    sync::OnceLock,
+};
+
+use crate::{
+    img::{Color, ColorSpace},
+    math::Vec3,
+};
+
+pub const LAMBDA_MIN: f64 = 360.;
+pub const LAMBDA_MAX: f64 = 830.;
+pub const SPECTRUM_SAMPLES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampledWavelengths {
+    lambda: [f64; SPECTRUM_SAMPLES],
+    pdf: [f64; SPECTRUM_SAMPLES],
+}
+
+impl SampledWavelengths {
+    // hero wavelength sampling: one uniform sample with the rest equally spaced after it
+    pub fn sample_uniform(u: f64) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let hero = LAMBDA_MIN + u * range;
+        let lambda = std::array::from_fn(|i| {
+            let lambda = hero + i as f64 * range / SPECTRUM_SAMPLES as f64;
+            if lambda > LAMBDA_MAX {
+                lambda - range
+            } else {
+                lambda
+            }
+        });
+        Self {
+            lambda,
+            pdf: [range.recip(); SPECTRUM_SAMPLES],
+        }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    pub fn lambda(&self) -> &[f64; SPECTRUM_SAMPLES] {
+        &self.lambda
+    }
+
+    pub fn is_secondary_terminated(&self) -> bool {
+        self.pdf[1..].iter().all(|&pdf| pdf == 0.)
+    }
+
+    // wavelength dependent scattering can only follow one of the wavelengths
+    pub fn terminate_secondary(&mut self) {
+        if self.is_secondary_terminated() {
+            return;
+        }
+        self.pdf[1..].fill(0.);
+        self.pdf[0] /= SPECTRUM_SAMPLES as f64;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampledSpectrum(pub [f64; SPECTRUM_SAMPLES]);
+
+impl SampledSpectrum {
+    pub const fn splat(v: f64) -> Self {
+        Self([v; SPECTRUM_SAMPLES])
+    }
+
+    pub fn from_fn(lambdas: &SampledWavelengths, f: impl Fn(f64) -> f64) -> Self {
+        Self(lambdas.lambda.map(f))
+    }
+
+    pub fn from_rgb(color: Color, lambdas: &SampledWavelengths) -> Self {
+        Self::from_fn(lambdas, |lambda| rgb_to_spectrum(color, lambda))
+    }
+
+    pub fn to_xyz(self, lambdas: &SampledWavelengths) -> Vec3 {
+        let sum = self
+            .0
+            .iter()
+            .zip(lambdas.lambda)
+            .zip(lambdas.pdf)
+            .filter(|(_, pdf)| *pdf != 0.)
+            .fold(Vec3::ZERO, |sum, ((s, lambda), pdf)| {
+                sum + cie_xyz(lambda) * (s / pdf)
+            });
+        let white = cie_white();
+        let xyz = sum / (SPECTRUM_SAMPLES as f64 * white.y);
+        // white balance the equal energy illuminant to D65
+        Vec3::new(
+            xyz.x * 0.95047 * white.y / white.x,
+            xyz.y,
+            xyz.z * 1.08883 * white.y / white.z,
+        )
+    }
+
+    pub fn to_color(self, lambdas: &SampledWavelengths, color_space: ColorSpace) -> Color {
+        Color(&color_space.xyz_to_rgb() * self.to_xyz(lambdas))
+    }
+}
+
+impl Mul for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(mut self, rhs: Self) -> Self::Output {
+        for (s, r) in self.0.iter_mut().zip(rhs.0) {
+            *s *= r;
+        }
+        self
+    }
+}
+
+impl Mul<f64> for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        Self(self.0.map(|s| s * rhs))
+    }
+}
+
+impl Add for SampledSpectrum {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        for (s, r) in self.0.iter_mut().zip(rhs.0) {
+            *s += r;
+        }
+        self
+    }
+}
+
+fn piecewise_gaussian(x: f64, mean: f64, sigma_low: f64, sigma_high: f64) -> f64 {
+    let sigma = if x < mean { sigma_low } else { sigma_high };
+    (-0.5 * ((x - mean) / sigma).powi(2)).exp()
+}
+
+// Wyman, Sloan and Shirley's multi-lobe fit of the CIE 1931 color matching functions
+pub fn cie_xyz(lambda: f64) -> Vec3 {
+    Vec3::new(
+        1.056 * piecewise_gaussian(lambda, 599.8, 37.9, 31.0)
+            + 0.362 * piecewise_gaussian(lambda, 442.0, 16.0, 26.7)
+            - 0.065 * piecewise_gaussian(lambda, 501.1, 20.4, 26.2),
+        0.821 * piecewise_gaussian(lambda, 568.8, 46.9, 40.5)
+            + 0.286 * piecewise_gaussian(lambda, 530.9, 16.3, 31.1),
+        1.217 * piecewise_gaussian(lambda, 437.0, 11.8, 36.0)
+            + 0.681 * piecewise_gaussian(lambda, 459.0, 26.0, 13.8),
+    )
+}
+
+// integrals of the color matching functions over the sampled range
+fn cie_white() -> Vec3 {
+    static WHITE: OnceLock<Vec3> = OnceLock::new();
+    *WHITE.get_or_init(|| {
+        let steps = (LAMBDA_MAX - LAMBDA_MIN) as usize * 4;
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+        (0..steps)
+            .map(|i| cie_xyz(LAMBDA_MIN + (i as f64 + 0.5) * step) * step)
+            .fold(Vec3::ZERO, |sum, v| sum + v)
+    })
+}
+
+// Smits' "An RGB to Spectrum Conversion for Reflectances" basis spectra,
+// sampled in 10 bins from 380nm to 720nm
+const SMITS_WHITE: [f64; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f64; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f64; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f64; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f64; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f64; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f64; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+fn smits_basis(basis: &[f64; 10], lambda: f64) -> f64 {
+    let bin = ((lambda - 380.) / (720. - 380.) * 9.).clamp(0., 9.);
+    let low = (bin.floor() as usize).min(8);
+    let t = bin - low as f64;
+    basis[low] * (1. - t) + basis[low + 1] * t
+}
+
+pub fn rgb_to_spectrum(color: Color, lambda: f64) -> f64 {
+    let (r, g, b) = (color.r(), color.g(), color.b());
+    let basis = |basis| smits_basis(basis, lambda);
+    if r <= g && r <= b {
+        let secondary = if g <= b {
+            (g - r) * basis(&SMITS_CYAN) + (b - g) * basis(&SMITS_BLUE)
+        } else {
+            (b - r) * basis(&SMITS_CYAN) + (g - b) * basis(&SMITS_GREEN)
+        };
+        r * basis(&SMITS_WHITE) + secondary
+    } else if g <= r && g <= b {
+        let secondary = if r <= b {
+            (r - g) * basis(&SMITS_MAGENTA) + (b - r) * basis(&SMITS_BLUE)
+        } else {
+            (b - g) * basis(&SMITS_MAGENTA) + (r - b) * basis(&SMITS_RED)
+        };
+        g * basis(&SMITS_WHITE) + secondary
+    } else {
+        let secondary = if r <= g {
+            (r - b) * basis(&SMITS_YELLOW) + (g - r) * basis(&SMITS_GREEN)
+        } else {
+            (g - b) * basis(&SMITS_YELLOW) + (r - g) * basis(&SMITS_RED)
+        };
+        b * basis(&SMITS_WHITE) + secondary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SampledSpectrum, SampledWavelengths};
+    use crate::img::{Color, ColorSpace};
+
+    // averages many wavelength samples so the estimator noise vanishes
+    fn film_color(color: Color) -> Color {
+        const N: usize = 2000;
+        let sum = (0..N)
+            .map(|i| {
+                let lambdas = SampledWavelengths::sample_uniform((i as f64 + 0.5) / N as f64);
+                SampledSpectrum::from_rgb(color, &lambdas).to_color(&lambdas, ColorSpace::Srgb)
+            })
+            .fold(Color::BLACK.0, |sum, c| sum + c.0);
+        Color(sum / N as f64)
+    }
+
+    #[test]
+    fn test_gray_round_trip() {
+        for v in [1., 0.5, 0.1] {
+            let color = film_color(Color::gray(v));
+            assert!(
+                (color.0 - Color::gray(v).0).l1_norm() < 0.03 * v,
+                "{color:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_primaries_keep_hue() {
+        let red = film_color(Color::RED);
+        assert!(red.r() > 0.5 && red.g() < 0.1 && red.b() < 0.1, "{red:?}");
+        let blue = film_color(Color::BLUE);
+        assert!(
+            blue.b() > 0.5 && blue.r() < 0.1 && blue.g() < 0.1,
+            "{blue:?}"
+        );
+    }
+
+    #[test]
+    fn test_terminate_secondary() {
+        let mut lambdas = SampledWavelengths::sample_uniform(0.3);
+        assert!(!lambdas.is_secondary_terminated());
+        lambdas.terminate_secondary();
+        assert!(lambdas.is_secondary_terminated());
+        assert!(SampledSpectrum::splat(1.).to_xyz(&lambdas).y > 0.);
+    }
+}
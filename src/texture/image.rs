@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use crate::{
+    img::{Color, ColorSpace, Image},
+    math::{Vec2, Vec3},
+};
+
+use super::Texture;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextureFilter {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    Mirror,
+    Clamp,
+}
+
+impl WrapMode {
+    fn wrap(&self, i: isize, len: usize) -> usize {
+        let len = len as isize;
+        let wrapped = match self {
+            WrapMode::Repeat => i.rem_euclid(len),
+            WrapMode::Mirror => {
+                let i = i.rem_euclid(2 * len);
+                if i < len {
+                    i
+                } else {
+                    2 * len - 1 - i
+                }
+            }
+            WrapMode::Clamp => i.clamp(0, len - 1),
+        };
+        wrapped as usize
+    }
+}
+
+/// Samples an image with uv (0, 0) at its bottom left corner
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    image: Arc<Image>,
+    pub filter: TextureFilter,
+    pub wrap: WrapMode,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image, working_space: ColorSpace) -> Self {
+        Self {
+            image: Arc::new(image.to_color_space(working_space)),
+            filter: TextureFilter::default(),
+            wrap: WrapMode::default(),
+        }
+    }
+
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    fn texel(&self, x: isize, y: isize) -> Color {
+        let (width, height) = (self.image.width(), self.image.height());
+        // there is nothing to wrap around in an empty image
+        if width == 0 || height == 0 {
+            return Color::BLACK;
+        }
+        let x = self.wrap.wrap(x, width);
+        let y = self.wrap.wrap(y, height);
+        // rows are stored top to bottom
+        self.image.data()[(height - y - 1) * width + x]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn sample(&self, uv: Vec2, _point: Vec3) -> Color {
+        let x = uv.x * self.image.width() as f64;
+        let y = uv.y * self.image.height() as f64;
+        match self.filter {
+            TextureFilter::Nearest => self.texel(x.floor() as isize, y.floor() as isize),
+            TextureFilter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as isize, y0 as isize);
+                let bottom = self.texel(x0, y0).0 * (1. - tx) + self.texel(x0 + 1, y0).0 * tx;
+                let top = self.texel(x0, y0 + 1).0 * (1. - tx) + self.texel(x0 + 1, y0 + 1).0 * tx;
+                Color(bottom * (1. - ty) + top * ty)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        img::{Color, ColorSpace, Image},
+        math::{Vec2, Vec3},
+        scene::Display,
+        texture::Texture,
+    };
+
+    use super::{ImageTexture, TextureFilter, WrapMode};
+
+    #[test]
+    fn test_wrap_modes() {
+        let indices = |wrap: WrapMode| (-4..7).map(|i| wrap.wrap(i, 3)).collect::<Vec<_>>();
+        assert_eq!(indices(WrapMode::Repeat), [2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(indices(WrapMode::Mirror), [2, 2, 1, 0, 0, 1, 2, 2, 1, 0, 0]);
+        assert_eq!(indices(WrapMode::Clamp), [0, 0, 0, 0, 0, 1, 2, 2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_bilinear() {
+        // 0 1 on the top row, 2 3 on the bottom row
+        let mut img = Image::zeros(Display::new(2, 2));
+        for (i, color) in img.data_mut().iter_mut().enumerate() {
+            *color = Color::gray(i as f64);
+        }
+        let texture = ImageTexture::new(img, ColorSpace::Srgb).with_wrap(WrapMode::Clamp);
+        let sample = |texture: &ImageTexture, u: f64, v: f64| {
+            texture.sample(Vec2::new(u, v), Vec3::ZERO).r()
+        };
+        let cases = [
+            // texel centers
+            (0.25, 0.25, 2.),
+            (0.75, 0.75, 1.),
+            (0.5, 0.5, 1.5),
+            (0.375, 0.25, 2.25),
+            (0.25, 0.375, 1.5),
+            // clamped past the edge
+            (0., 0., 2.),
+        ];
+        for (u, v, expected) in cases {
+            assert!((sample(&texture, u, v) - expected).abs() < 1e-9, "{u} {v}");
+        }
+        // repeating blends the left edge with the right column
+        let repeat = texture.clone().with_wrap(WrapMode::Repeat);
+        assert!((sample(&repeat, 0., 0.25) - 2.5).abs() < 1e-9);
+        let nearest = texture.with_filter(TextureFilter::Nearest);
+        assert_eq!(sample(&nearest, 0.9, 0.1), 3.);
+    }
+
+    #[test]
+    fn test_empty_image() {
+        let texture = ImageTexture::new(Image::zeros(Display::new(0, 0)), ColorSpace::Srgb);
+        assert_eq!(
+            texture.sample(Vec2::new(0.3, 0.7), Vec3::ZERO),
+            Color::BLACK
+        );
+    }
+}
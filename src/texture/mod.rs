@@ -0,0 +1,23 @@
+use crate::{
+    img::Color,
+    math::{Vec2, Vec3},
+};
+
+mod image;
+mod normal_map;
+mod procedural;
+
+pub use image::{ImageTexture, TextureFilter, WrapMode};
+pub use normal_map::NormalMap;
+pub use procedural::{Checker, NoisePattern, NoiseTexture};
+
+/// A color source looked up at the hit point of a shape
+pub trait Texture {
+    fn sample(&self, uv: Vec2, point: Vec3) -> Color;
+}
+
+impl Texture for Color {
+    fn sample(&self, _uv: Vec2, _point: Vec3) -> Color {
+        *self
+    }
+}
@@ -0,0 +1,110 @@
+use std::{fmt::Debug, sync::Arc};
+
+use crate::math::{Vec2, Vec3};
+
+use super::{ImageTexture, Texture};
+
+// uv and world space step for the bump map finite differences
+const BUMP_DELTA: f64 = 1e-3;
+
+/// Perturbs the shading normal of a surface
+#[derive(Clone)]
+pub enum NormalMap {
+    // rgb in [0, 1] encodes a tangent space normal, the image should be non-color data
+    TangentSpace(ImageTexture),
+    // the luminance of the texture is used as the surface height
+    Bump {
+        height: Arc<dyn Texture + Send + Sync>,
+        strength: f64,
+    },
+}
+
+impl NormalMap {
+    // tangent and bitangent point along increasing u and v
+    pub fn perturb(
+        &self,
+        normal: Vec3,
+        tangent: Vec3,
+        bitangent: Vec3,
+        uv: Vec2,
+        point: Vec3,
+    ) -> Vec3 {
+        match self {
+            NormalMap::TangentSpace(texture) => {
+                let encoded = texture.sample(uv, point).0;
+                let local = encoded * 2. - Vec3::splat(1.);
+                (tangent * local.x + bitangent * local.y + normal * local.z).normalize_or_zero()
+            }
+            NormalMap::Bump { height, strength } => {
+                let height_at = |uv: Vec2, point: Vec3| height.sample(uv, point).luminance();
+                let base = height_at(uv, point);
+                let du = (height_at(uv + Vec2::new(BUMP_DELTA, 0.), point + tangent * BUMP_DELTA)
+                    - base)
+                    / BUMP_DELTA;
+                let dv = (height_at(
+                    uv + Vec2::new(0., BUMP_DELTA),
+                    point + bitangent * BUMP_DELTA,
+                ) - base)
+                    / BUMP_DELTA;
+                (normal - (tangent * du + bitangent * dv) * *strength).normalize_or_zero()
+            }
+        }
+    }
+}
+
+impl Debug for NormalMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TangentSpace(texture) => f.debug_tuple("TangentSpace").field(texture).finish(),
+            Self::Bump { strength, .. } => f
+                .debug_struct("Bump")
+                .field("strength", strength)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        img::{Color, ColorSpace, Image},
+        math::{assert_close, Vec2, Vec3},
+        scene::Display,
+        texture::ImageTexture,
+    };
+
+    use super::NormalMap;
+
+    fn tangent_space(encoded: Color) -> NormalMap {
+        let mut img = Image::zeros(Display::new(1, 1));
+        *img.at_mut(0, 0) = encoded;
+        NormalMap::TangentSpace(ImageTexture::new(img.non_color(), ColorSpace::Srgb))
+    }
+
+    #[test]
+    fn test_flat_maps_keep_the_normal() {
+        let (normal, tangent, bitangent) = (Vec3::Y, Vec3::X, Vec3::NEG_Z);
+        let uv = Vec2::new(0.3, 0.6);
+        let flat = tangent_space(Color::from_rgb(0.5, 0.5, 1.));
+        assert_close(
+            flat.perturb(normal, tangent, bitangent, uv, Vec3::ZERO),
+            normal,
+        );
+        // fully tilted toward the tangent
+        let tilted = tangent_space(Color::from_rgb(1., 0.5, 0.5));
+        assert_close(
+            tilted.perturb(normal, tangent, bitangent, uv, Vec3::ZERO),
+            tangent,
+        );
+        let bump = NormalMap::Bump {
+            height: Arc::new(Color::gray(0.7)),
+            strength: 2.,
+        };
+        assert_close(
+            bump.perturb(normal, tangent, bitangent, uv, Vec3::ZERO),
+            normal,
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{
+    img::Color,
+    math::{Vec2, Vec3},
+    rand::RandSource,
+};
+
+use super::Texture;
+
+/// Alternates between two textures in a 3D grid of `size` sided cubes
+#[derive(Debug, Clone)]
+pub struct Checker<A: Texture, B: Texture> {
+    pub size: f64,
+    pub even: A,
+    pub odd: B,
+}
+
+impl<A: Texture, B: Texture> Checker<A, B> {
+    pub const fn new(size: f64, even: A, odd: B) -> Self {
+        Self { size, even, odd }
+    }
+}
+
+impl<A: Texture, B: Texture> Texture for Checker<A, B> {
+    fn sample(&self, uv: Vec2, point: Vec3) -> Color {
+        let cell = point
+            .into_iter()
+            .map(|x| (x / self.size).floor() as i64)
+            .sum::<i64>();
+        if cell.rem_euclid(2) == 0 {
+            self.even.sample(uv, point)
+        } else {
+            self.odd.sample(uv, point)
+        }
+    }
+}
+
+/// Ken Perlin's improved gradient noise, in roughly `[-1, 1]`
+#[derive(Debug, Clone)]
+pub struct Perlin {
+    permutation: [u8; 512],
+}
+
+impl Perlin {
+    pub fn new(rng: &mut impl RandSource) -> Self {
+        let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+        for i in (1..table.len()).rev() {
+            let j = rng.rand::<u32>() as usize % (i + 1);
+            table.swap(i, j);
+        }
+        Self {
+            permutation: std::array::from_fn(|i| table[i % 256]),
+        }
+    }
+
+    pub fn noise(&self, point: Vec3) -> f64 {
+        let cell = [point.x.floor(), point.y.floor(), point.z.floor()];
+        let [x, y, z] = [point.x - cell[0], point.y - cell[1], point.z - cell[2]];
+        let [xi, yi, zi] = [0, 1, 2].map(|i| (cell[i] as i64).rem_euclid(256) as usize);
+        let p = &self.permutation;
+        let hash = |dx: usize, dy: usize, dz: usize| {
+            p[p[p[xi + dx] as usize + yi + dy] as usize + zi + dz]
+        };
+        let [u, v, w] = [fade(x), fade(y), fade(z)];
+        let lerp = |t: f64, a: f64, b: f64| a + t * (b - a);
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(hash(0, 0, 0), x, y, z),
+                    grad(hash(1, 0, 0), x - 1., y, z),
+                ),
+                lerp(
+                    u,
+                    grad(hash(0, 1, 0), x, y - 1., z),
+                    grad(hash(1, 1, 0), x - 1., y - 1., z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(hash(0, 0, 1), x, y, z - 1.),
+                    grad(hash(1, 0, 1), x - 1., y, z - 1.),
+                ),
+                lerp(
+                    u,
+                    grad(hash(0, 1, 1), x, y - 1., z - 1.),
+                    grad(hash(1, 1, 1), x - 1., y - 1., z - 1.),
+                ),
+            ),
+        )
+    }
+
+    // fractal brownian motion, each octave doubling the frequency and halving the amplitude
+    pub fn fbm(&self, point: Vec3, octaves: u32) -> f64 {
+        self.octaves(point, octaves, |noise| noise)
+    }
+
+    pub fn turbulence(&self, point: Vec3, octaves: u32) -> f64 {
+        self.octaves(point, octaves, f64::abs)
+    }
+
+    fn octaves(&self, point: Vec3, octaves: u32, f: impl Fn(f64) -> f64) -> f64 {
+        (0..octaves)
+            .map(|octave| {
+                let frequency = 2f64.powi(octave as i32);
+                f(self.noise(point * frequency)) / frequency
+            })
+            .sum()
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn grad(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    let u = if h & 1 == 0 { u } else { -u };
+    let v = if h & 2 == 0 { v } else { -v };
+    u + v
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoisePattern {
+    Noise,
+    Fbm { octaves: u32 },
+    Turbulence { octaves: u32 },
+    // veins along the x axis, distorted by turbulence
+    Marble { octaves: u32, distortion: f64 },
+    // rings around the y axis
+    Wood { rings: f64, distortion: f64 },
+}
+
+/// Blends between two colors based on a noise pattern
+#[derive(Debug, Clone)]
+pub struct NoiseTexture {
+    pub perlin: Perlin,
+    pub pattern: NoisePattern,
+    pub scale: f64,
+    pub low: Color,
+    pub high: Color,
+}
+
+impl NoiseTexture {
+    pub fn new(
+        rng: &mut impl RandSource,
+        pattern: NoisePattern,
+        scale: f64,
+        low: Color,
+        high: Color,
+    ) -> Self {
+        Self {
+            perlin: Perlin::new(rng),
+            pattern,
+            scale,
+            low,
+            high,
+        }
+    }
+
+    pub fn value(&self, point: Vec3) -> f64 {
+        let point = point * self.scale;
+        let value = match self.pattern {
+            NoisePattern::Noise => 0.5 * (self.perlin.noise(point) + 1.),
+            NoisePattern::Fbm { octaves } => 0.5 * (self.perlin.fbm(point, octaves) + 1.),
+            NoisePattern::Turbulence { octaves } => self.perlin.turbulence(point, octaves),
+            NoisePattern::Marble {
+                octaves,
+                distortion,
+            } => {
+                let turbulence = self.perlin.turbulence(point, octaves);
+                0.5 * (1. + (point.x + distortion * turbulence).sin())
+            }
+            NoisePattern::Wood { rings, distortion } => {
+                let radius = (point.x * point.x + point.z * point.z).sqrt();
+                let grain = rings * radius + distortion * self.perlin.noise(point);
+                grain - grain.floor()
+            }
+        };
+        value.clamp(0., 1.)
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn sample(&self, _uv: Vec2, point: Vec3) -> Color {
+        let t = self.value(point);
+        Color(self.low.0 * (1. - t) + self.high.0 * t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NoisePattern, NoiseTexture, Perlin};
+    use crate::{img::Color, math::Vec3, rand::JavaLcg};
+
+    #[test]
+    fn test_seeded_noise_is_reproducible() {
+        let a = Perlin::new(&mut JavaLcg::from_seed(7));
+        let b = Perlin::new(&mut JavaLcg::from_seed(7));
+        let c = Perlin::new(&mut JavaLcg::from_seed(8));
+        let points = (0..50).map(|i| Vec3::new(i as f64 * 0.37, i as f64 * 0.11, -1.3));
+        let mut differs = false;
+        for point in points {
+            assert_eq!(a.noise(point), b.noise(point));
+            differs |= a.noise(point) != c.noise(point);
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn test_noise_is_zero_on_lattice_and_bounded() {
+        let perlin = Perlin::new(&mut JavaLcg::from_seed(1));
+        assert_eq!(perlin.noise(Vec3::new(3., -2., 5.)), 0.);
+        for i in 0..1000 {
+            let point = Vec3::new(i as f64 * 0.173, i as f64 * -0.071, i as f64 * 0.029);
+            assert!(perlin.noise(point).abs() <= 1.);
+        }
+    }
+
+    #[test]
+    fn test_patterns_stay_in_range() {
+        let patterns = [
+            NoisePattern::Noise,
+            NoisePattern::Fbm { octaves: 5 },
+            NoisePattern::Turbulence { octaves: 5 },
+            NoisePattern::Marble {
+                octaves: 5,
+                distortion: 4.,
+            },
+            NoisePattern::Wood {
+                rings: 8.,
+                distortion: 2.,
+            },
+        ];
+        for pattern in patterns {
+            let texture = NoiseTexture::new(
+                &mut JavaLcg::from_seed(3),
+                pattern,
+                2.,
+                Color::BLACK,
+                Color::WHITE,
+            );
+            for i in 0..200 {
+                let value = texture.value(Vec3::new(i as f64 * 0.13, 0.5, i as f64 * 0.07));
+                assert!((0. ..=1.).contains(&value));
+            }
+        }
+    }
+}
@@ -176,6 +176,7 @@ mod tests {
     use super::ChunkIter;
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn test_cartesian_product() {
         let a = vec![1, 2, 3];
         let b = vec![4, 5, 6];